@@ -0,0 +1,414 @@
+use std::time::Duration;
+
+use futures::{Stream, TryStreamExt};
+
+use crate::{
+    query_params, ApiError, GoogleSearchError, Image, SearchItem, SearchRequest, SearchResponse,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://customsearch.googleapis.com/customsearch/v1";
+pub const DEFAULT_USER_AGENT: &str = concat!("search-google/", env!("CARGO_PKG_VERSION"));
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// The most results a single request returns.
+pub const PAGE_SIZE: usize = 10;
+/// The API never returns results past this one, `start + num` must not exceed it.
+pub const MAX_RESULTS: usize = 100;
+
+/// A Custom Search client, cheap to clone and meant to be reused so connections are pooled.
+#[derive(Debug, Clone)]
+pub struct GoogleSearchClient {
+    api_key: String,
+    engine_id: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl GoogleSearchClient {
+    /// A client for the engine `engine_id` with the default base URL, timeout and user agent.
+    pub fn new(
+        api_key: impl Into<String>,
+        engine_id: impl Into<String>,
+    ) -> Result<Self, GoogleSearchError> {
+        Self::builder(api_key, engine_id).build()
+    }
+
+    /// Reads `GOOGLE_SEARCH_API_KEY` and `GOOGLE_SEARCH_ENGINE_ID`.
+    pub fn from_env() -> Result<Self, GoogleSearchError> {
+        Self::new(
+            api_key_from_env()?,
+            std::env::var("GOOGLE_SEARCH_ENGINE_ID")
+                .map_err(|_| GoogleSearchError::MissingCredentials("GOOGLE_SEARCH_ENGINE_ID"))?,
+        )
+    }
+
+    /// Reads `GOOGLE_SEARCH_API_KEY` and searches the engine `cx`, reading
+    /// `GOOGLE_SEARCH_ENGINE_ID` only when that is `None`.
+    pub fn from_env_for(cx: Option<&str>) -> Result<Self, GoogleSearchError> {
+        match cx {
+            Some(cx) => Self::new(api_key_from_env()?, cx),
+            None => Self::from_env(),
+        }
+    }
+
+    pub fn builder(
+        api_key: impl Into<String>,
+        engine_id: impl Into<String>,
+    ) -> GoogleSearchClientBuilder {
+        GoogleSearchClientBuilder {
+            api_key: api_key.into(),
+            engine_id: engine_id.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            client: None,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Searches the client's engine, or `search_request.cx` if that is set.
+    pub async fn search(
+        &self,
+        search_request: SearchRequest,
+    ) -> Result<SearchResponse, GoogleSearchError> {
+        let engine_id = search_request
+            .cx
+            .clone()
+            .unwrap_or_else(|| self.engine_id.clone());
+        let mut params = vec![("key", self.api_key.clone()), ("cx", engine_id)];
+        params.extend(query_params(search_request));
+
+        let response = self
+            .client
+            .get(&self.base_url)
+            .query(&params)
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(GoogleSearchError::Api(ApiError::parse(
+                status.as_u16(),
+                &body,
+            )));
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// The pages of results for `search_request`, until `limit` results, the last page or the
+    /// API's 100 result ceiling is reached.
+    ///
+    /// Paging starts at `search_request.start`, its `num` is ignored.
+    pub fn pages(
+        &self,
+        search_request: SearchRequest,
+        limit: usize,
+    ) -> impl Stream<Item = Result<SearchResponse, GoogleSearchError>> + '_ {
+        let start = search_request
+            .start
+            .as_deref()
+            .and_then(|start| start.parse().ok())
+            .unwrap_or(1);
+        futures::stream::try_unfold(Some((start, limit)), move |next| {
+            let search_request = search_request.clone();
+            async move {
+                let Some((start, remaining)) = next else {
+                    return Ok(None);
+                };
+                let num = remaining
+                    .min(PAGE_SIZE)
+                    .min(MAX_RESULTS.saturating_sub(start));
+                if num == 0 {
+                    return Ok(None);
+                }
+                let response = self
+                    .search(SearchRequest {
+                        start: Some(start.to_string()),
+                        num: Some(num.to_string()),
+                        ..search_request
+                    })
+                    .await?;
+                let remaining = remaining.saturating_sub(response.items.len());
+                let next = match response.queries.next_page.first() {
+                    Some(page) if remaining > 0 && !response.items.is_empty() => {
+                        Some((page.start_index as usize, remaining))
+                    }
+                    _ => None,
+                };
+                Ok(Some((response, next)))
+            }
+        })
+    }
+
+    /// Up to `limit` results for `search_request`, from as many pages as needed.
+    pub async fn search_all(
+        &self,
+        search_request: SearchRequest,
+        limit: usize,
+    ) -> Result<Vec<SearchItem>, GoogleSearchError> {
+        let mut items: Vec<SearchItem> = self
+            .pages(search_request, limit)
+            .map_ok(|response| response.items)
+            .try_concat()
+            .await?;
+        items.truncate(limit);
+        Ok(items)
+    }
+
+    /// Downloads the thumbnail of an image search result.
+    pub async fn thumbnail(&self, image: &Image) -> Result<Vec<u8>, GoogleSearchError> {
+        let response = self
+            .client
+            .get(&image.thumbnail_link)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+fn api_key_from_env() -> Result<String, GoogleSearchError> {
+    std::env::var("GOOGLE_SEARCH_API_KEY")
+        .map_err(|_| GoogleSearchError::MissingCredentials("GOOGLE_SEARCH_API_KEY"))
+}
+
+pub struct GoogleSearchClientBuilder {
+    api_key: String,
+    engine_id: String,
+    base_url: String,
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+    client: Option<reqwest::Client>,
+}
+
+impl GoogleSearchClientBuilder {
+    /// Where to send requests instead of the Custom Search API, e.g. a local mock server.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// The timeout of a whole request, 30 seconds by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Shares an existing client, whose own timeouts and user agent are used instead.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn build(self) -> Result<GoogleSearchClient, GoogleSearchError> {
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let mut builder = reqwest::Client::builder()
+                    .timeout(self.timeout)
+                    .user_agent(self.user_agent);
+                if let Some(connect_timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(connect_timeout);
+                }
+                builder.build()?
+            }
+        };
+        Ok(GoogleSearchClient {
+            api_key: self.api_key,
+            engine_id: self.engine_id,
+            base_url: self.base_url,
+            client,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    /// A page of `count` results starting at `start`, with a next page unless `last`.
+    fn page(start: usize, count: usize, last: bool) -> serde_json::Value {
+        let query = |start: usize| {
+            serde_json::json!({
+                "title": "Google Custom Search - rust",
+                "totalResults": "23",
+                "searchTerms": "rust",
+                "count": count,
+                "startIndex": start,
+                "inputEncoding": "utf8",
+                "outputEncoding": "utf8",
+                "safe": "off",
+                "cx": "engine"
+            })
+        };
+        let items = (start..start + count)
+            .map(|i| {
+                serde_json::json!({
+                    "kind": "customsearch#result",
+                    "title": format!("Result {}", i),
+                    "link": format!("https://example.com/{}", i),
+                    "snippet": ""
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut queries = serde_json::json!({ "request": [query(start)] });
+        if !last {
+            queries["nextPage"] = serde_json::json!([query(start + count)]);
+        }
+        serde_json::json!({
+            "kind": "customsearch#search",
+            "url": { "type": "application/json", "template": "" },
+            "queries": queries,
+            "context": { "title": "engine" },
+            "items": items
+        })
+    }
+
+    async fn mount_page(server: &MockServer, start: usize, num: usize, count: usize, last: bool) {
+        Mock::given(method("GET"))
+            .and(query_param("start", start.to_string().as_str()))
+            .and(query_param("num", num.to_string().as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page(start, count, last)))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    fn client(server: &MockServer) -> GoogleSearchClient {
+        GoogleSearchClient::builder("secret", "engine")
+            .base_url(server.uri())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_search_all_stops_on_last_page() {
+        let server = MockServer::start().await;
+        mount_page(&server, 1, 10, 10, false).await;
+        mount_page(&server, 11, 10, 10, false).await;
+        mount_page(&server, 21, 10, 3, true).await;
+
+        let request = SearchRequest {
+            q: Some("rust".to_string()),
+            ..Default::default()
+        };
+        let items = client(&server).search_all(request, 50).await.unwrap();
+        assert_eq!(items.len(), 23);
+        assert_eq!(items[22].title, "Result 23");
+    }
+
+    #[tokio::test]
+    async fn test_search_all_respects_limit_and_ceiling() {
+        let server = MockServer::start().await;
+        mount_page(&server, 1, 10, 10, false).await;
+        mount_page(&server, 11, 5, 5, false).await;
+        mount_page(&server, 91, 9, 9, false).await;
+
+        let request = SearchRequest {
+            q: Some("rust".to_string()),
+            ..Default::default()
+        };
+        let client = client(&server);
+        let items = client.search_all(request.clone(), 15).await.unwrap();
+        assert_eq!(items.len(), 15);
+        // 91 + 9 is the last page the API serves, even though it reports a next one.
+        let request = SearchRequest {
+            start: Some("91".to_string()),
+            ..request
+        };
+        let items = client.search_all(request, 50).await.unwrap();
+        assert_eq!(items.len(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_custom_client() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("key", "secret"))
+            .and(query_param("cx", "other-engine"))
+            .and(query_param("q", "rust"))
+            .and(header("user-agent", "search-tests/1.0"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+                "error": {
+                    "code": 403,
+                    "message": "This project does not have the access to Custom Search JSON API.",
+                    "errors": [{ "domain": "global", "reason": "forbidden" }],
+                    "status": "PERMISSION_DENIED"
+                }
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = GoogleSearchClient::builder("secret", "engine")
+            .base_url(server.uri())
+            .timeout(Duration::from_secs(5))
+            .user_agent("search-tests/1.0")
+            .build()
+            .unwrap();
+        let request = SearchRequest {
+            q: Some("rust".to_string()),
+            cx: Some("other-engine".to_string()),
+            ..Default::default()
+        };
+        // The same connection pool serves both requests.
+        for _ in 0..2 {
+            let e = client.search(request.clone()).await.unwrap_err();
+            let GoogleSearchError::Api(error) = e else {
+                panic!("expected an API error, got {:?}", e);
+            };
+            assert!(error.has_reason("forbidden"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/images"))
+            .and(query_param("q", "tbn:abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"\xff\xd8\xff".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = GoogleSearchClient::new("secret", "engine").unwrap();
+        let image: Image = serde_json::from_value(serde_json::json!({
+            "contextLink": "https://example.com/ferris",
+            "height": 600,
+            "width": 800,
+            "byteSize": 51234,
+            "thumbnailLink": format!("{}/images?q=tbn:abc", server.uri()),
+            "thumbnailHeight": 108,
+            "thumbnailWidth": 144
+        }))
+        .unwrap();
+        assert_eq!(client.thumbnail(&image).await.unwrap(), b"\xff\xd8\xff");
+
+        let missing = Image {
+            thumbnail_link: format!("{}/missing", server.uri()),
+            ..image
+        };
+        assert!(matches!(
+            client.thumbnail(&missing).await,
+            Err(GoogleSearchError::Http(_))
+        ));
+    }
+}
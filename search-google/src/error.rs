@@ -0,0 +1,162 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+/// Everything that can go wrong searching Google.
+#[derive(Debug)]
+pub enum GoogleSearchError {
+    /// The environment variable holding the API key or the engine ID isn't set.
+    MissingCredentials(&'static str),
+    /// Google couldn't be reached or the response couldn't be read.
+    Http(reqwest::Error),
+    /// Google answered with an error, e.g. an invalid key or an exhausted quota.
+    Api(ApiError),
+    /// The response isn't a search response.
+    Decode(serde_json::Error),
+}
+
+/// The `error` object of a Google API error response, see
+/// <https://cloud.google.com/apis/design/errors#http_mapping>.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiError {
+    pub code: u16,
+    #[serde(default)]
+    pub message: String,
+    /// The canonical status, e.g. `RESOURCE_EXHAUSTED`.
+    pub status: Option<String>,
+    #[serde(default)]
+    pub errors: Vec<ApiErrorDetail>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiErrorDetail {
+    #[serde(default)]
+    pub domain: String,
+    /// E.g. `dailyLimitExceeded`, `rateLimitExceeded` or `keyInvalid`.
+    #[serde(default)]
+    pub reason: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    error: ApiError,
+}
+
+impl ApiError {
+    /// Reads the `{"error": ..}` envelope Custom Search answers with, falling back to the body
+    /// itself as the message when it isn't one.
+    pub fn parse(status: u16, body: &str) -> Self {
+        match serde_json::from_str::<Envelope>(body) {
+            Ok(envelope) => envelope.error,
+            Err(_) => ApiError {
+                code: status,
+                message: body.trim().to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The `reason` of every entry in `errors`.
+    pub fn reasons(&self) -> impl Iterator<Item = &str> {
+        self.errors.iter().map(|error| error.reason.as_str())
+    }
+
+    pub fn has_reason(&self, reason: &str) -> bool {
+        self.reasons().any(|r| r == reason)
+    }
+
+    /// The daily or per-minute quota is used up, retrying later may succeed.
+    pub fn is_quota_exceeded(&self) -> bool {
+        self.code == 429
+            || self.reasons().any(|reason| {
+                matches!(
+                    reason,
+                    "dailyLimitExceeded" | "rateLimitExceeded" | "userRateLimitExceeded"
+                )
+            })
+    }
+
+    pub fn is_key_invalid(&self) -> bool {
+        self.has_reason("keyInvalid") || self.message.contains("API key not valid")
+    }
+}
+
+impl fmt::Display for GoogleSearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoogleSearchError::MissingCredentials(variable) => {
+                write!(f, "{} environment variable not set", variable)
+            }
+            GoogleSearchError::Http(e) => write!(f, "could not reach Google: {}", e),
+            GoogleSearchError::Api(e) => {
+                write!(f, "Google API error ({}): {}", e.code, e.message)?;
+                let reasons = e.reasons().collect::<Vec<_>>();
+                if !reasons.is_empty() {
+                    write!(f, " [{}]", reasons.join(", "))?;
+                }
+                Ok(())
+            }
+            GoogleSearchError::Decode(e) => {
+                write!(f, "could not decode the Google response: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GoogleSearchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GoogleSearchError::Http(e) => Some(e),
+            GoogleSearchError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for GoogleSearchError {
+    fn from(e: reqwest::Error) -> Self {
+        GoogleSearchError::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for GoogleSearchError {
+    fn from(e: serde_json::Error) -> Self {
+        GoogleSearchError::Decode(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_api_error() {
+        let body = r#"{
+            "error": {
+                "code": 429,
+                "message": "Quota exceeded for quota metric 'Queries' and limit 'Queries per day'.",
+                "errors": [{
+                    "message": "Quota exceeded for quota metric 'Queries' and limit 'Queries per day'.",
+                    "domain": "usageLimits",
+                    "reason": "dailyLimitExceeded"
+                }],
+                "status": "RESOURCE_EXHAUSTED"
+            }
+        }"#;
+        let error = ApiError::parse(429, body);
+        assert!(error.has_reason("dailyLimitExceeded"));
+        assert!(error.is_quota_exceeded());
+        assert!(!error.is_key_invalid());
+        assert_eq!(error.status.as_deref(), Some("RESOURCE_EXHAUSTED"));
+        assert_eq!(
+            GoogleSearchError::Api(error).to_string(),
+            "Google API error (429): Quota exceeded for quota metric 'Queries' and limit 'Queries per day'. [dailyLimitExceeded]"
+        );
+
+        let error = ApiError::parse(502, "<html>Bad Gateway</html>");
+        assert_eq!(error.code, 502);
+        assert_eq!(error.message, "<html>Bad Gateway</html>");
+    }
+}
@@ -1,13 +1,41 @@
 use clap::Parser;
 use serde::{Deserialize, Serialize};
+
+mod client;
+mod error;
+mod params;
+#[cfg(any(test, feature = "replay"))]
+pub mod replay;
+
+pub use client::{
+    GoogleSearchClient, GoogleSearchClientBuilder, DEFAULT_BASE_URL, MAX_RESULTS, PAGE_SIZE,
+};
+pub use error::{ApiError, ApiErrorDetail, GoogleSearchError};
+pub use params::{
+    C2coff, Country, DateRestrict, DateUnit, Filter, ImgColorType, ImgDominantColor, ImgSize,
+    ImgType, Language, Rights, Safe, SearchType, SiteSearchFilter,
+};
+
+/// Fields the API adds beyond the ones modelled here, kept so schema drift doesn't break parsing.
+pub type Extras = serde_json::Map<String, serde_json::Value>;
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SearchResponse {
     pub kind: String,
     pub url: Url,
     pub queries: Queries,
     pub context: Context,
+    #[serde(rename = "searchInformation")]
     pub search_information: Option<SearchInformation>,
+    /// A suggested spelling of the query.
+    pub spelling: Option<Spelling>,
+    #[serde(default)]
+    pub promotions: Vec<Promotion>,
+    /// Missing when nothing matches.
+    #[serde(default)]
     pub items: Vec<SearchItem>,
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -19,12 +47,18 @@ pub struct Url {
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Queries {
+    #[serde(default)]
     pub request: Vec<Request>,
-    #[serde(rename = "nextPage")]
+    /// Missing on the first page.
+    #[serde(rename = "previousPage", default)]
+    pub previous_page: Vec<NextPage>,
+    /// Missing on the last page.
+    #[serde(rename = "nextPage", default)]
     pub next_page: Vec<NextPage>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct NextPage {
     pub title: String,
     #[serde(rename = "totalResults")]
@@ -59,17 +93,95 @@ pub struct SearchInformation {
     pub formatted_total_results: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Spelling {
+    #[serde(rename = "correctedQuery")]
+    pub corrected_query: String,
+    #[serde(rename = "htmlCorrectedQuery")]
+    pub html_corrected_query: Option<String>,
+}
+
+/// A result the engine's owner pinned for the query.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Promotion {
+    pub title: String,
+    #[serde(rename = "htmlTitle")]
+    pub html_title: Option<String>,
+    pub link: String,
+    #[serde(rename = "displayLink")]
+    pub display_link: Option<String>,
+    #[serde(rename = "bodyLines", default)]
+    pub body_lines: Vec<PromotionBodyLine>,
+    pub image: Option<PromotionImage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PromotionBodyLine {
+    pub title: Option<String>,
+    #[serde(rename = "htmlTitle")]
+    pub html_title: Option<String>,
+    pub url: Option<String>,
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PromotionImage {
+    pub source: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SearchItem {
     pub kind: String,
     pub title: String,
+    #[serde(rename = "htmlTitle")]
+    pub html_title: Option<String>,
     pub link: String,
+    #[serde(rename = "displayLink")]
+    pub display_link: Option<String>,
+    /// Missing for some documents, e.g. images and files without text.
+    #[serde(default)]
     pub snippet: String,
+    #[serde(rename = "htmlSnippet")]
+    pub html_snippet: Option<String>,
     #[serde(rename = "cacheId")]
     pub cache_id: Option<String>,
+    #[serde(rename = "formattedUrl")]
+    pub formatted_url: Option<String>,
+    #[serde(rename = "htmlFormattedUrl")]
+    pub html_formatted_url: Option<String>,
+    /// The MIME type of files other than web pages, e.g. `application/pdf`.
+    pub mime: Option<String>,
+    #[serde(rename = "fileFormat")]
+    pub file_format: Option<String>,
+    /// Structured data found on the page, keyed by type, e.g. `metatags` or `cse_thumbnail`.
+    pub pagemap: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Set for image searches.
+    pub image: Option<Image>,
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Image {
+    /// The page the image appears on.
+    #[serde(rename = "contextLink")]
+    pub context_link: String,
+    pub width: i64,
+    pub height: i64,
+    #[serde(rename = "byteSize")]
+    pub byte_size: Option<i64>,
+    #[serde(rename = "thumbnailLink")]
+    pub thumbnail_link: String,
+    #[serde(rename = "thumbnailWidth")]
+    pub thumbnail_width: Option<i64>,
+    #[serde(rename = "thumbnailHeight")]
+    pub thumbnail_height: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct Request {
     pub title: String,
     #[serde(rename = "totalResults")]
@@ -87,132 +199,180 @@ pub struct Request {
     pub cx: String,
 }
 
-#[derive(Debug, Parser, Serialize, Clone)]
+/// The parameters of a search, which double as the command line of `search-google` and `us google`.
+///
+/// Common options have a short flag; `-h`, `-n` and `-t` are left to the command lines embedding
+/// it. Help is grouped into web, filter and image options.
+#[derive(Debug, Default, Parser, Serialize, Clone)]
 pub struct SearchRequest {
-    #[clap(short, long, help = "Search Query")]
+    #[clap(value_name = "QUERY", help = "Search Query")]
     pub q: Option<String>,
+
     #[clap(
         short,
         long,
-        help = "Enables (0 default) or disables (1) Simplified and Traditional Chinese Search."
+        help_heading = "Web",
+        help = "Results must contain this exact phrase"
     )]
-    pub c2coff: Option<String>,
-    #[clap(short, long, help = "Country Restriction")]
-    pub cr: Option<String>, // Country Restriction
-    #[clap(short, long, help = "Custom Search Engine ID")]
-    pub cx: Option<String>, // Custom Search Engine ID
-    #[clap(short, long, help = "Date Restrict")]
-    pub date_restrict: Option<String>, // Date Restrict
-    #[clap(short, long, help = "Exact Terms")]
-    pub exact_terms: Option<String>, // Exact Terms
-    #[clap(short, long, help = "Exclude Terms")]
-    pub exclude_terms: Option<String>, // Exclude Terms
-    #[clap(short, long, help = "File Type")]
-    pub file_type: Option<String>, // File Type
-    #[clap(short, long, help = "Filter for duplicate content 0 or 1, default 0")]
-    pub filter: Option<String>, // Filter for duplicate content 0 or 1, default 0
-    #[clap(short, long, help = "Geolocation of end user")]
-    pub gl: Option<String>, // Geolocation of end user
-    #[clap(short, long, help = "Highest post in range")]
-    pub high_range: Option<String>, // Highest post in range
-    #[clap(short, long, help = "Interface Language")]
-    pub hl: Option<String>, // Interface Language
+    pub exact_terms: Option<String>,
+    #[clap(
+        short = 'x',
+        long,
+        help_heading = "Web",
+        help = "Results must not contain these words"
+    )]
+    pub exclude_terms: Option<String>,
     #[clap(
-        short,
         long,
-        help = "Appends the specified query terms to the query, as if they were combined with a logical AND operator."
+        help_heading = "Web",
+        help = "Results must contain at least one of these words"
+    )]
+    pub or_terms: Option<String>,
+    #[clap(
+        long,
+        help_heading = "Web",
+        help = "Appended to the query as if combined with a logical AND"
     )]
     pub hq: Option<String>,
-    #[clap(short, long, help = "Image Color Type")]
-    pub img_color_type: Option<String>, // Image Color Type
-    #[clap(short, long, help = "Image Dominant Color")]
-    pub img_dominant_color: Option<String>, // Image Dominant Color
-    #[clap(short, long, help = "Image Size")]
-    pub img_size: Option<String>, // Image Size
-    #[clap(short, long, help = "Image Type")]
-    pub img_type: Option<String>, // Image Type: clipart, face, lineart, stock, photo, animated
     #[clap(
         short,
         long,
-        help = "Specifies that all search results should contain a link to a particular URL"
+        help_heading = "Web",
+        help = "Only results from this site"
     )]
-    pub link_site: Option<String>, // Specifies that all search results should contain a link to a particular URL
-    #[clap(short, long, help = "Lowest post in range")]
-    pub low_range: Option<String>, // Specifies the starting value for a search range. Use lowRange and highRange to append an inclusive search range of lowRange...highRange to the query.
-    #[clap(short, long, help = "Language Restrict")]
-    pub lr: Option<String>, // Language Restrict
-    #[clap(short, long, help = "Number of search results to return 1-10")]
-    pub num: Option<String>, // Number of search results to return 1-10
+    pub site_search: Option<String>,
     #[clap(
-        short,
         long,
-        help = "Provides additional search terms to check for in a document, where each document in the search results must contain at least one of the additional search terms."
+        help_heading = "Web",
+        requires = "site_search",
+        help = "Whether --site-search includes (default) or excludes the site"
     )]
-    pub or_terms: Option<String>,
+    pub site_search_filter: Option<SiteSearchFilter>,
+    #[clap(short, long, help_heading = "Web", help = "File Type, e.g. pdf")]
+    pub file_type: Option<String>,
+    #[clap(long, help_heading = "Web", help = "Results must link to this URL")]
+    pub link_site: Option<String>,
     #[clap(
-        short,
         long,
-        help = "Specifies that all search results should be pages that are related to the specified URL"
+        help_heading = "Web",
+        help = "Results must be related to this URL"
     )]
     pub related_site: Option<String>,
+    #[clap(
+        long,
+        help_heading = "Web",
+        help = "Lowest value of an inclusive search range"
+    )]
+    pub low_range: Option<String>,
+    #[clap(
+        long,
+        help_heading = "Web",
+        help = "Highest value of an inclusive search range"
+    )]
+    pub high_range: Option<String>,
+    #[clap(long, help_heading = "Web", help = "Sorts results, e.g. date")]
+    pub sort: Option<String>,
     #[clap(
         short,
         long,
-        help = "Filters based on licensing. Supported values include: cc_publicdomain, cc_attribute, cc_sharealike, cc_noncommercial, cc_nonderived"
+        help_heading = "Web",
+        help = "Geolocation of end user, e.g. us"
     )]
-    pub rights: Option<String>,
-    #[clap(short, long, help = "Search Safety Level")]
-    pub safe: Option<String>,
-    #[clap(short, long, help = "Search Type (image, news, video)")]
-    pub search_type: Option<String>,
+    pub gl: Option<String>,
+    #[clap(long, help_heading = "Web", help = "Interface Language")]
+    pub hl: Option<String>,
+    #[clap(long, help_heading = "Web", help = "Custom Search Engine ID")]
+    pub cx: Option<String>,
+    #[clap(
+        long,
+        help_heading = "Web",
+        help = "Number of search results to return 1-10"
+    )]
+    pub num: Option<String>,
+    #[clap(
+        long,
+        help_heading = "Web",
+        help = "The index of the first result to return, 11 starts the second page. start + num may not exceed 100"
+    )]
+    pub start: Option<String>,
+
     #[clap(
         short,
         long,
-        help = "Specifies all search results should be pages from a given site"
+        help_heading = "Filters",
+        help = "Date Restrict: d, w, m or y followed by a number, e.g. d7 for the past week"
     )]
-    pub site_search: Option<String>,
+    pub date_restrict: Option<DateRestrict>,
     #[clap(
         short,
         long,
-        help = "Controls whether to include or exclude results from the site named in the sitesearch parameter"
+        help_heading = "Filters",
+        help = "Language Restrict, a language code like en or lang_en"
     )]
-    pub site_search_filter: Option<String>,
-    #[clap(short, long, help = "Sorts results")]
-    pub sort: Option<String>,
+    pub lr: Option<Language>,
     #[clap(
         short,
         long,
-        help = "The index of the first result to return. The default number of results per page is 10, so &start=11 would start at the top of the second page of results. Note: The JSON API will never return more than 100 results, even if more than 100 documents match the query, so setting the sum of start + num to a number greater than 100 will produce an error. Also note that the maximum value for num is 10."
+        help_heading = "Filters",
+        help = "Country Restriction, a two letter country code like IN or countryIN"
     )]
-    pub start: Option<String>,
+    pub cr: Option<Country>,
+    #[clap(
+        short = 'S',
+        long,
+        help_heading = "Filters",
+        help = "Search Safety Level"
+    )]
+    pub safe: Option<Safe>,
+    #[clap(long, help_heading = "Filters", help = "Filter for duplicate content")]
+    pub filter: Option<Filter>,
+    #[clap(long, help_heading = "Filters", help = "Filters based on licensing")]
+    pub rights: Option<Rights>,
+    #[clap(
+        long,
+        help_heading = "Filters",
+        help = "Enables (default) or disables Simplified and Traditional Chinese Search"
+    )]
+    pub c2coff: Option<C2coff>,
+
+    #[clap(
+        long,
+        help_heading = "Image",
+        help = "Search Type, web search unless set to image"
+    )]
+    pub search_type: Option<SearchType>,
+    #[clap(long, help_heading = "Image", help = "Image Size")]
+    pub img_size: Option<ImgSize>,
+    #[clap(long, help_heading = "Image", help = "Image Type")]
+    pub img_type: Option<ImgType>,
+    #[clap(long, help_heading = "Image", help = "Image Color Type")]
+    pub img_color_type: Option<ImgColorType>,
+    #[clap(long, help_heading = "Image", help = "Image Dominant Color")]
+    pub img_dominant_color: Option<ImgDominantColor>,
 }
 
-pub async fn search(
-    // search_query: &str,
-    search_request: SearchRequest,
-) -> Result<SearchResponse, Box<dyn std::error::Error>> {
-    let url = "https://customsearch.googleapis.com/customsearch/v1";
-    let api_key = std::env::var("GOOGLE_SEARCH_API_KEY")
-        .expect("GOOGLE_SEARCH_API_KEY environment variable not set");
-    let engine_id = std::env::var("GOOGLE_SEARCH_ENGINE_ID")
-        .expect("GOOGLE_SEARCH_ENGINE_ID environment variable not set"); // a7f51c925c0474034
+/// Searches with the key in `GOOGLE_SEARCH_API_KEY` and the engine in `search_request.cx`,
+/// or `GOOGLE_SEARCH_ENGINE_ID` if that is not set.
+///
+/// Every call builds a new `GoogleSearchClient`, which is worth keeping around for more than a
+/// one-off query.
+pub async fn search(search_request: SearchRequest) -> Result<SearchResponse, GoogleSearchError> {
+    GoogleSearchClient::from_env_for(search_request.cx.as_deref())?
+        .search(search_request)
+        .await
+}
 
-    let mut query_params = vec![
-        ("key", api_key),
-        ("cx", engine_id),
-        ("q", search_request.q.unwrap_or("".to_string())),
-    ];
+/// The query parameters of `search_request`, except for the key and the engine ID.
+fn query_params(search_request: SearchRequest) -> Vec<(&'static str, String)> {
+    let mut query_params = vec![("q", search_request.q.unwrap_or("".to_string()))];
     if let Some(c2coff) = search_request.c2coff {
-        query_params.push(("c2coff", c2coff));
+        query_params.push(("c2coff", c2coff.to_string()));
     }
     if let Some(cr) = search_request.cr {
-        query_params.push(("cr", cr));
-    }
-    if let Some(cx) = search_request.cx {
-        query_params.push(("cx", cx));
+        query_params.push(("cr", cr.to_string()));
     }
     if let Some(date_restrict) = search_request.date_restrict {
-        query_params.push(("dateRestrict", date_restrict));
+        query_params.push(("dateRestrict", date_restrict.to_string()));
     }
     if let Some(exact_terms) = search_request.exact_terms {
         query_params.push(("exactTerms", exact_terms));
@@ -224,7 +384,7 @@ pub async fn search(
         query_params.push(("fileType", file_type));
     }
     if let Some(filter) = search_request.filter {
-        query_params.push(("filter", filter));
+        query_params.push(("filter", filter.to_string()));
     }
     if let Some(gl) = search_request.gl {
         query_params.push(("gl", gl));
@@ -239,16 +399,16 @@ pub async fn search(
         query_params.push(("hq", hq));
     }
     if let Some(img_color_type) = search_request.img_color_type {
-        query_params.push(("imgColorType", img_color_type));
+        query_params.push(("imgColorType", img_color_type.to_string()));
     }
     if let Some(img_dominant_color) = search_request.img_dominant_color {
-        query_params.push(("imgDominantColor", img_dominant_color));
+        query_params.push(("imgDominantColor", img_dominant_color.to_string()));
     }
     if let Some(img_size) = search_request.img_size {
-        query_params.push(("imgSize", img_size));
+        query_params.push(("imgSize", img_size.to_string()));
     }
     if let Some(img_type) = search_request.img_type {
-        query_params.push(("imgType", img_type));
+        query_params.push(("imgType", img_type.to_string()));
     }
     if let Some(link_site) = search_request.link_site {
         query_params.push(("linkSite", link_site));
@@ -257,7 +417,7 @@ pub async fn search(
         query_params.push(("lowRange", low_range));
     }
     if let Some(lr) = search_request.lr {
-        query_params.push(("lr", lr));
+        query_params.push(("lr", lr.to_string()));
     }
     if let Some(num) = search_request.num {
         query_params.push(("num", num));
@@ -269,19 +429,19 @@ pub async fn search(
         query_params.push(("relatedSite", related_site));
     }
     if let Some(rights) = search_request.rights {
-        query_params.push(("rights", rights));
+        query_params.push(("rights", rights.to_string()));
     }
     if let Some(safe) = search_request.safe {
-        query_params.push(("safe", safe));
+        query_params.push(("safe", safe.to_string()));
     }
     if let Some(search_type) = search_request.search_type {
-        query_params.push(("searchType", search_type));
+        query_params.push(("searchType", search_type.to_string()));
     }
     if let Some(site_search) = search_request.site_search {
         query_params.push(("siteSearch", site_search));
     }
     if let Some(site_search_filter) = search_request.site_search_filter {
-        query_params.push(("siteSearchFilter", site_search_filter));
+        query_params.push(("siteSearchFilter", site_search_filter.to_string()));
     }
     if let Some(sort) = search_request.sort {
         query_params.push(("sort", sort));
@@ -290,14 +450,7 @@ pub async fn search(
         query_params.push(("start", start));
     }
 
-    let search_response: SearchResponse = reqwest::Client::new()
-        .get(url)
-        .query(&query_params)
-        .send()
-        .await?
-        .json()
-        .await?;
-    Ok(search_response)
+    query_params
 }
 
 #[cfg(test)]
@@ -309,14 +462,14 @@ mod tests {
         println!("Running test_search_with_params");
         let search_request = SearchRequest {
             q: Some("reliance industries".to_string()),
-            c2coff: Some("1".to_string()),
-            cr: Some("countryIN".to_string()),
+            c2coff: Some(C2coff::Disabled),
+            cr: Some("IN".parse().unwrap()),
             cx: None,
-            date_restrict: Some("d1".to_string()), // d1: Past 24 hours, w1: Past week, m1: Past month, y1: Past year
+            date_restrict: Some("d1".parse().unwrap()), // d1: Past 24 hours, w1: Past week, m1: Past month, y1: Past year
             exact_terms: None,
             exclude_terms: None,
             file_type: None,
-            filter: Some("1".to_string()),
+            filter: Some(Filter::On),
             gl: None,
             high_range: None,
             hl: None,
@@ -327,7 +480,7 @@ mod tests {
             img_type: None,
             link_site: None,
             low_range: None,
-            lr: Some("lang_en".to_string()),
+            lr: Some("en".parse().unwrap()),
             num: None,
             or_terms: None,
             related_site: None,
@@ -339,7 +492,8 @@ mod tests {
             sort: None,
             start: None,
         };
-        let search_response = search(search_request).await.unwrap();
+        let (_server, client) = replay::replay("reliance_industries", &search_request).await;
+        let search_response = client.search(search_request).await.unwrap();
         println!(
             "total_results: {}",
             search_response.queries.request[0].total_results
@@ -357,5 +511,64 @@ mod tests {
         }
         assert_eq!(search_response.kind, "customsearch#search");
         assert_eq!(search_response.items.len(), 10);
+        assert_eq!(
+            search_response.items[0].html_title.as_deref(),
+            Some("<b>Reliance Industries</b> Limited")
+        );
+        assert_eq!(
+            search_response.search_information.unwrap().total_results,
+            "1340000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_without_results() {
+        let search_request = SearchRequest {
+            q: Some("rsut asyncc trait objcts".to_string()),
+            ..Default::default()
+        };
+        let (_server, client) = replay::replay("no_results", &search_request).await;
+        let search_response = client.search(search_request).await.unwrap();
+        assert!(search_response.items.is_empty());
+        assert!(search_response.queries.next_page.is_empty());
+        assert_eq!(search_response.queries.request[0].start_index, 0);
+        assert_eq!(
+            search_response.spelling.unwrap().corrected_query,
+            "rust async trait objects"
+        );
+        assert_eq!(search_response.promotions[0].body_lines.len(), 1);
+        assert!(search_response.extras.contains_key("queryExpansion"));
+    }
+
+    #[tokio::test]
+    async fn test_search_engine_from_cx() {
+        let search_request = SearchRequest {
+            q: Some("rsut asyncc trait objcts".to_string()),
+            cx: Some("test-engine".to_string()),
+            ..Default::default()
+        };
+        // The stub only answers for `test-engine`, not the engine the client was built with.
+        let (server, _) = replay::replay("no_results", &search_request).await;
+        let client = GoogleSearchClient::builder("test-key", "other-engine")
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let search_response = client.search(search_request).await.unwrap();
+        assert!(search_response.items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_quota_exceeded() {
+        let search_request = SearchRequest {
+            q: Some("rust".to_string()),
+            ..Default::default()
+        };
+        let (_server, client) = replay::replay("quota_exceeded", &search_request).await;
+        let e = client.search(search_request).await.unwrap_err();
+        let GoogleSearchError::Api(error) = e else {
+            panic!("expected an API error, got {:?}", e);
+        };
+        assert_eq!(error.code, 429);
+        assert!(error.is_quota_exceeded());
     }
 }
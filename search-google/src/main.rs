@@ -1,14 +1,190 @@
+use std::path::{Path, PathBuf};
+
 use clap::Parser;
 
-use search_google::{search, SearchRequest};
+use search_google::{GoogleSearchClient, GoogleSearchError, SearchItem, SearchRequest, SearchType};
+
+use crate::preview::Protocol;
+
+mod preview;
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "search-google",
+    about = "Search Google with the Custom Search JSON API",
+    long_about = None,
+    mut_arg("q", |arg| arg.required(true))
+)]
+struct Cli {
+    #[clap(flatten)]
+    request: SearchRequest,
+    #[clap(
+        short = 'n',
+        long,
+        help = "Fetch up to this many results, following the pages 10 at a time (at most 100)"
+    )]
+    limit: Option<usize>,
+    #[clap(
+        short,
+        long,
+        help_heading = "Image",
+        help = "Search images, short for --search-type image"
+    )]
+    images: bool,
+    #[clap(
+        long,
+        value_name = "DIR",
+        help_heading = "Image",
+        help = "Download the thumbnails of image results into this directory"
+    )]
+    thumbnails: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "PROTOCOL",
+        num_args = 0..=1,
+        default_missing_value = "auto",
+        help_heading = "Image",
+        help = "Show thumbnails of image results inline, on terminals speaking kitty graphics or sixel"
+    )]
+    preview: Option<Protocol>,
+}
+
+async fn run(client: &GoogleSearchClient, cli: Cli) -> Result<Vec<SearchItem>, GoogleSearchError> {
+    match cli.limit {
+        Some(limit) => client.search_all(cli.request, limit).await,
+        None => Ok(client.search(cli.request).await?.items),
+    }
+}
+
+/// Saves a thumbnail as `{dir}/{rank}.{ext}`, with the extension of the format it is in.
+fn save_thumbnail(dir: &Path, rank: usize, bytes: &[u8]) -> std::io::Result<PathBuf> {
+    let extension = image::guess_format(bytes)
+        .ok()
+        .and_then(|format| format.extensions_str().first())
+        .unwrap_or(&"jpg");
+    let path = dir.join(format!("{:02}.{}", rank, extension));
+    std::fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+fn exit_with(e: impl std::fmt::Display) -> ! {
+    eprintln!("{}", e);
+    std::process::exit(1);
+}
 
 #[tokio::main]
 async fn main() {
-    let google_search_request: SearchRequest = SearchRequest::parse();
-    let google_search_response = search(google_search_request).await.unwrap();
-    for item in google_search_response.items {
+    let mut cli = Cli::parse();
+    if cli.images {
+        cli.request.search_type = Some(SearchType::Image);
+    }
+    if let Some(dir) = &cli.thumbnails {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            exit_with(format!("could not create {}: {}", dir.display(), e));
+        }
+    }
+    let thumbnails = cli.thumbnails.clone();
+    let preview = cli.preview.and_then(|protocol| {
+        let resolved = protocol.resolve();
+        if resolved.is_none() {
+            eprintln!("can't tell whether the terminal shows images, pass --preview kitty or --preview sixel");
+        }
+        resolved
+    });
+
+    let client = GoogleSearchClient::from_env_for(cli.request.cx.as_deref())
+        .unwrap_or_else(|e| exit_with(e));
+    let items = run(&client, cli).await.unwrap_or_else(|e| exit_with(e));
+    for (i, item) in items.into_iter().enumerate() {
         println!("Title: {}", item.title);
         println!("Link: {}", item.link);
-        println!("Snippet: {}", item.snippet);
+        let Some(image) = item.image else {
+            println!("Snippet: {}", item.snippet);
+            continue;
+        };
+        println!("Page: {}", image.context_link);
+        match image.byte_size {
+            Some(byte_size) => println!(
+                "Size: {}x{}, {} bytes",
+                image.width, image.height, byte_size
+            ),
+            None => println!("Size: {}x{}", image.width, image.height),
+        }
+        if thumbnails.is_none() && preview.is_none() {
+            println!("Thumbnail: {}", image.thumbnail_link);
+            continue;
+        }
+        let bytes = match client.thumbnail(&image).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("could not download {}: {}", image.thumbnail_link, e);
+                continue;
+            }
+        };
+        if let Some(dir) = &thumbnails {
+            match save_thumbnail(dir, i + 1, &bytes) {
+                Ok(path) => println!("Thumbnail: {}", path.display()),
+                Err(e) => eprintln!("could not save the thumbnail of {}: {}", item.link, e),
+            }
+        }
+        if let Some(protocol) = preview {
+            match preview::render(&bytes, protocol) {
+                Ok(escape) => println!("{}", escape),
+                Err(e) => eprintln!("could not preview {}: {}", image.thumbnail_link, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+    use search_google::{DateUnit, Safe};
+
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from([
+            "search-google",
+            "rust async",
+            "-d",
+            "w2",
+            "-s",
+            "docs.rs",
+            "-l",
+            "de",
+            "-S",
+            "active",
+            "-n",
+            "20",
+        ]);
+        assert_eq!(cli.request.q.as_deref(), Some("rust async"));
+        assert_eq!(cli.request.date_restrict.unwrap().unit, DateUnit::Week);
+        assert_eq!(cli.request.site_search.as_deref(), Some("docs.rs"));
+        assert_eq!(cli.request.lr.unwrap().to_string(), "lang_de");
+        assert_eq!(cli.request.safe, Some(Safe::Active));
+        assert_eq!(cli.limit, Some(20));
+        assert!(Cli::try_parse_from(["search-google"]).is_err());
+        let cli = Cli::parse_from([
+            "search-google",
+            "ferris",
+            "-i",
+            "--thumbnails",
+            "out",
+            "--preview",
+        ]);
+        assert!(cli.images);
+        assert_eq!(cli.thumbnails, Some(PathBuf::from("out")));
+        assert_eq!(cli.preview, Some(Protocol::Auto));
+        let cli = Cli::parse_from(["search-google", "ferris", "--preview", "sixel"]);
+        assert_eq!(
+            cli.preview.and_then(Protocol::resolve),
+            Some(Protocol::Sixel)
+        );
+        assert!(
+            Cli::try_parse_from(["search-google", "rust", "--site-search-filter", "e"]).is_err()
+        );
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,457 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Safe {
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "off")]
+    Off,
+}
+
+impl Display for Safe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Safe::Active => write!(f, "active"),
+            Safe::Off => write!(f, "off"),
+        }
+    }
+}
+
+/// The API only knows image search; web search is the default.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SearchType {
+    #[serde(rename = "image")]
+    Image,
+}
+
+impl Display for SearchType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchType::Image => write!(f, "image"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImgSize {
+    #[serde(rename = "icon")]
+    Icon,
+    #[serde(rename = "small")]
+    Small,
+    #[serde(rename = "medium")]
+    Medium,
+    #[serde(rename = "large")]
+    Large,
+    #[serde(rename = "xlarge")]
+    Xlarge,
+    #[serde(rename = "xxlarge")]
+    Xxlarge,
+    #[serde(rename = "huge")]
+    Huge,
+}
+
+impl Display for ImgSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImgSize::Icon => write!(f, "icon"),
+            ImgSize::Small => write!(f, "small"),
+            ImgSize::Medium => write!(f, "medium"),
+            ImgSize::Large => write!(f, "large"),
+            ImgSize::Xlarge => write!(f, "xlarge"),
+            ImgSize::Xxlarge => write!(f, "xxlarge"),
+            ImgSize::Huge => write!(f, "huge"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImgType {
+    #[serde(rename = "clipart")]
+    Clipart,
+    #[serde(rename = "face")]
+    Face,
+    #[serde(rename = "lineart")]
+    Lineart,
+    #[serde(rename = "stock")]
+    Stock,
+    #[serde(rename = "photo")]
+    Photo,
+    #[serde(rename = "animated")]
+    Animated,
+}
+
+impl Display for ImgType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImgType::Clipart => write!(f, "clipart"),
+            ImgType::Face => write!(f, "face"),
+            ImgType::Lineart => write!(f, "lineart"),
+            ImgType::Stock => write!(f, "stock"),
+            ImgType::Photo => write!(f, "photo"),
+            ImgType::Animated => write!(f, "animated"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImgColorType {
+    #[serde(rename = "color")]
+    Color,
+    #[serde(rename = "gray")]
+    Gray,
+    #[serde(rename = "mono")]
+    Mono,
+    #[serde(rename = "trans")]
+    Trans,
+}
+
+impl Display for ImgColorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImgColorType::Color => write!(f, "color"),
+            ImgColorType::Gray => write!(f, "gray"),
+            ImgColorType::Mono => write!(f, "mono"),
+            ImgColorType::Trans => write!(f, "trans"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImgDominantColor {
+    #[serde(rename = "black")]
+    Black,
+    #[serde(rename = "blue")]
+    Blue,
+    #[serde(rename = "brown")]
+    Brown,
+    #[serde(rename = "gray")]
+    Gray,
+    #[serde(rename = "green")]
+    Green,
+    #[serde(rename = "orange")]
+    Orange,
+    #[serde(rename = "pink")]
+    Pink,
+    #[serde(rename = "purple")]
+    Purple,
+    #[serde(rename = "red")]
+    Red,
+    #[serde(rename = "teal")]
+    Teal,
+    #[serde(rename = "white")]
+    White,
+    #[serde(rename = "yellow")]
+    Yellow,
+}
+
+impl Display for ImgDominantColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImgDominantColor::Black => write!(f, "black"),
+            ImgDominantColor::Blue => write!(f, "blue"),
+            ImgDominantColor::Brown => write!(f, "brown"),
+            ImgDominantColor::Gray => write!(f, "gray"),
+            ImgDominantColor::Green => write!(f, "green"),
+            ImgDominantColor::Orange => write!(f, "orange"),
+            ImgDominantColor::Pink => write!(f, "pink"),
+            ImgDominantColor::Purple => write!(f, "purple"),
+            ImgDominantColor::Red => write!(f, "red"),
+            ImgDominantColor::Teal => write!(f, "teal"),
+            ImgDominantColor::White => write!(f, "white"),
+            ImgDominantColor::Yellow => write!(f, "yellow"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Rights {
+    #[serde(rename = "cc_publicdomain")]
+    #[value(name = "cc_publicdomain")]
+    CcPublicdomain,
+    #[serde(rename = "cc_attribute")]
+    #[value(name = "cc_attribute")]
+    CcAttribute,
+    #[serde(rename = "cc_sharealike")]
+    #[value(name = "cc_sharealike")]
+    CcSharealike,
+    #[serde(rename = "cc_noncommercial")]
+    #[value(name = "cc_noncommercial")]
+    CcNoncommercial,
+    #[serde(rename = "cc_nonderived")]
+    #[value(name = "cc_nonderived")]
+    CcNonderived,
+}
+
+impl Display for Rights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rights::CcPublicdomain => write!(f, "cc_publicdomain"),
+            Rights::CcAttribute => write!(f, "cc_attribute"),
+            Rights::CcSharealike => write!(f, "cc_sharealike"),
+            Rights::CcNoncommercial => write!(f, "cc_noncommercial"),
+            Rights::CcNonderived => write!(f, "cc_nonderived"),
+        }
+    }
+}
+
+/// Whether near-duplicate results are left out; the API's `1` and `0` are accepted too.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Filter {
+    #[serde(rename = "1")]
+    #[value(alias = "1")]
+    On,
+    #[serde(rename = "0")]
+    #[value(alias = "0")]
+    Off,
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Filter::On => write!(f, "1"),
+            Filter::Off => write!(f, "0"),
+        }
+    }
+}
+
+/// Whether Simplified and Traditional Chinese results are searched together; the API's `0`
+/// (enabled) and `1` (disabled) are accepted too.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum C2coff {
+    #[serde(rename = "0")]
+    #[value(alias = "0")]
+    Enabled,
+    #[serde(rename = "1")]
+    #[value(alias = "1")]
+    Disabled,
+}
+
+impl Display for C2coff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            C2coff::Enabled => write!(f, "0"),
+            C2coff::Disabled => write!(f, "1"),
+        }
+    }
+}
+
+/// Whether `site_search` is the only site searched or the one left out; the API's `i` and
+/// `e` are accepted too.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SiteSearchFilter {
+    #[serde(rename = "i")]
+    #[value(alias = "i")]
+    Include,
+    #[serde(rename = "e")]
+    #[value(alias = "e")]
+    Exclude,
+}
+
+impl Display for SiteSearchFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SiteSearchFilter::Include => write!(f, "i"),
+            SiteSearchFilter::Exclude => write!(f, "e"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// Results from the last `count` days, weeks, months or years, written `d7`, `w2`, `m6` or `y1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRestrict {
+    pub unit: DateUnit,
+    pub count: u32,
+}
+
+impl FromStr for DateRestrict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("{} is not a date restriction like d7, w2, m6 or y1", s);
+        let mut chars = s.chars();
+        let unit = match chars.next() {
+            Some('d') => DateUnit::Day,
+            Some('w') => DateUnit::Week,
+            Some('m') => DateUnit::Month,
+            Some('y') => DateUnit::Year,
+            _ => return Err(invalid()),
+        };
+        let count = chars.as_str().parse().map_err(|_| invalid())?;
+        if count == 0 {
+            return Err(invalid());
+        }
+        Ok(DateRestrict { unit, count })
+    }
+}
+
+impl Display for DateRestrict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = match self.unit {
+            DateUnit::Day => 'd',
+            DateUnit::Week => 'w',
+            DateUnit::Month => 'm',
+            DateUnit::Year => 'y',
+        };
+        write!(f, "{}{}", unit, self.count)
+    }
+}
+
+/// The languages `lr` can restrict results to, see
+/// <https://developers.google.com/custom-search/v1/reference/rest/v1/cse/list#body.QUERY_PARAMETERS.lr>.
+const LANGUAGES: &[&str] = &[
+    "ar", "bg", "ca", "cs", "da", "de", "el", "en", "es", "et", "fi", "fr", "hr", "hu", "id", "is",
+    "it", "iw", "ja", "ko", "lt", "lv", "nl", "no", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv",
+    "tr", "zh-CN", "zh-TW",
+];
+
+/// A document language for `lr`, given as `de` or `lang_de`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Language(String);
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.strip_prefix("lang_").unwrap_or(s);
+        match LANGUAGES
+            .iter()
+            .find(|language| language.eq_ignore_ascii_case(code))
+        {
+            Some(language) => Ok(Language(language.to_string())),
+            None => Err(format!(
+                "{} is not a language Google can restrict to, one of {}",
+                s,
+                LANGUAGES.join(", ")
+            )),
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lang_{}", self.0)
+    }
+}
+
+/// The countries `cr` can restrict results to, see
+/// <https://developers.google.com/custom-search/v1/reference/rest/v1/cse/list#body.QUERY_PARAMETERS.cr>.
+const COUNTRIES: &[&str] = &[
+    "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AN", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AZ",
+    "BA", "BB", "BD", "BE", "BF", "BG", "BH", "BI", "BJ", "BM", "BN", "BO", "BR", "BS", "BT", "BV",
+    "BW", "BY", "BZ", "CA", "CC", "CD", "CF", "CG", "CH", "CI", "CK", "CL", "CM", "CN", "CO", "CR",
+    "CS", "CU", "CV", "CX", "CY", "CZ", "DE", "DJ", "DK", "DM", "DO", "DZ", "EC", "EE", "EG", "EH",
+    "ER", "ES", "ET", "EU", "FI", "FJ", "FK", "FM", "FO", "FR", "FX", "GA", "GD", "GE", "GF", "GH",
+    "GI", "GL", "GM", "GN", "GP", "GQ", "GR", "GS", "GT", "GU", "GW", "GY", "HK", "HM", "HN", "HR",
+    "HT", "HU", "ID", "IE", "IL", "IN", "IO", "IQ", "IR", "IS", "IT", "JM", "JO", "JP", "KE", "KG",
+    "KH", "KI", "KM", "KN", "KP", "KR", "KW", "KY", "KZ", "LA", "LB", "LC", "LI", "LK", "LR", "LS",
+    "LT", "LU", "LV", "LY", "MA", "MC", "MD", "MG", "MH", "MK", "ML", "MM", "MN", "MO", "MP", "MQ",
+    "MR", "MS", "MT", "MU", "MV", "MW", "MX", "MY", "MZ", "NA", "NC", "NE", "NF", "NG", "NI", "NL",
+    "NO", "NP", "NR", "NU", "NZ", "OM", "PA", "PE", "PF", "PG", "PH", "PK", "PL", "PM", "PN", "PR",
+    "PS", "PT", "PW", "PY", "QA", "RE", "RO", "RU", "RW", "SA", "SB", "SC", "SD", "SE", "SG", "SH",
+    "SI", "SJ", "SK", "SL", "SM", "SN", "SO", "SR", "ST", "SV", "SY", "SZ", "TC", "TD", "TF", "TG",
+    "TH", "TJ", "TK", "TM", "TN", "TO", "TP", "TR", "TT", "TV", "TW", "TZ", "UA", "UG", "UK", "UM",
+    "US", "UY", "UZ", "VA", "VC", "VE", "VG", "VI", "VN", "VU", "WF", "WS", "YE", "YT", "YU", "ZA",
+    "ZM", "ZW",
+];
+
+/// A country of origin for `cr`, given as a code of `COUNTRIES`, `IN` or `countryIN`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Country(String);
+
+impl FromStr for Country {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.strip_prefix("country").unwrap_or(s);
+        match COUNTRIES
+            .iter()
+            .find(|country| country.eq_ignore_ascii_case(code))
+        {
+            Some(country) => Ok(Country(country.to_string())),
+            None => Err(format!(
+                "{} is not a country Google can restrict to, a two letter code like IN",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for Country {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "country{}", self.0)
+    }
+}
+
+/// Serializes like the query parameter, so cache keys and logs read like the request.
+fn serialize_display<S: Serializer>(
+    value: &impl Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+impl Serialize for DateRestrict {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_display(self, serializer)
+    }
+}
+
+impl Serialize for Language {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_display(self, serializer)
+    }
+}
+
+impl Serialize for Country {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_display(self, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codes() {
+        let date_restrict: DateRestrict = "w2".parse().unwrap();
+        assert_eq!(date_restrict.unit, DateUnit::Week);
+        assert_eq!(date_restrict.to_string(), "w2");
+        assert!("x2".parse::<DateRestrict>().is_err());
+        assert!("d".parse::<DateRestrict>().is_err());
+        assert!("d0".parse::<DateRestrict>().is_err());
+
+        assert_eq!("de".parse::<Language>().unwrap().to_string(), "lang_de");
+        assert_eq!(
+            "lang_zh-tw".parse::<Language>().unwrap().to_string(),
+            "lang_zh-TW"
+        );
+        assert!("lang_xx".parse::<Language>().is_err());
+
+        assert_eq!("in".parse::<Country>().unwrap().to_string(), "countryIN");
+        assert_eq!(
+            "countryDE".parse::<Country>().unwrap().to_string(),
+            "countryDE"
+        );
+        assert!("India".parse::<Country>().is_err());
+        assert!("XX".parse::<Country>().is_err());
+
+        assert_eq!(Filter::from_str("1", true).unwrap(), Filter::On);
+        assert_eq!(
+            serde_json::to_value("d7".parse::<DateRestrict>().unwrap()).unwrap(),
+            "d7"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Inline image previews for terminals speaking the kitty graphics protocol or sixel.
+
+use std::fmt::Write;
+
+use base64::Engine;
+use clap::ValueEnum;
+use image::RgbaImage;
+
+/// The base64 payload is sent in chunks of at most this many bytes.
+const KITTY_CHUNK_SIZE: usize = 4096;
+/// Pixels more transparent than this are left unpainted in sixel.
+const SIXEL_ALPHA_THRESHOLD: u8 = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Protocol {
+    /// Detect the protocol from the environment.
+    Auto,
+    Kitty,
+    Sixel,
+}
+
+impl Protocol {
+    /// The protocol the terminal speaks, if `self` is `Auto` and it can be told from `TERM`,
+    /// `TERM_PROGRAM` or `KITTY_WINDOW_ID`.
+    pub fn resolve(self) -> Option<Protocol> {
+        if self != Protocol::Auto {
+            return Some(self);
+        }
+        let term = std::env::var("TERM").unwrap_or_default();
+        let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || ["WezTerm", "ghostty"].contains(&term_program.as_str())
+        {
+            Some(Protocol::Kitty)
+        } else if term.contains("sixel")
+            || ["foot", "mlterm", "contour"]
+                .iter()
+                .any(|name| term.starts_with(name))
+        {
+            Some(Protocol::Sixel)
+        } else {
+            None
+        }
+    }
+}
+
+/// The escape sequence showing the image in `bytes` with `protocol`; resolve `Auto` first, it
+/// falls back to kitty.
+pub fn render(bytes: &[u8], protocol: Protocol) -> image::ImageResult<String> {
+    let image = image::load_from_memory(bytes)?.to_rgba8();
+    Ok(match protocol {
+        Protocol::Kitty | Protocol::Auto => kitty(&image),
+        Protocol::Sixel => sixel(&image),
+    })
+}
+
+/// Transmits and displays raw RGBA pixels, split into chunks as the protocol requires.
+fn kitty(image: &RgbaImage) -> String {
+    let payload = base64::engine::general_purpose::STANDARD.encode(image.as_raw());
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap();
+        if i == 0 {
+            let _ = write!(
+                out,
+                "\x1b_Ga=T,f=32,s={},v={},m={};{}\x1b\\",
+                image.width(),
+                image.height(),
+                more,
+                chunk
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    out
+}
+
+/// The index of the colour closest to `r`, `g`, `b` in a 6x6x6 colour cube.
+fn cube_index(r: u8, g: u8, b: u8) -> usize {
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+/// Encodes the image with a fixed 216 colour palette, six rows per band.
+fn sixel(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    for index in 0..216 {
+        let percent = |level: usize| level * 100 / 5;
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            index,
+            percent(index / 36),
+            percent(index / 6 % 6),
+            percent(index % 6)
+        );
+    }
+    for band in (0..height).step_by(6) {
+        // For every colour in the band, the sixel bits of each column.
+        let mut colors: Vec<Option<Vec<u8>>> = vec![None; 216];
+        for y in band..(band + 6).min(height) {
+            for x in 0..width {
+                let [r, g, b, a] = image.get_pixel(x, y).0;
+                if a < SIXEL_ALPHA_THRESHOLD {
+                    continue;
+                }
+                let columns =
+                    colors[cube_index(r, g, b)].get_or_insert_with(|| vec![0; width as usize]);
+                columns[x as usize] |= 1 << (y - band);
+            }
+        }
+        let mut first = true;
+        for (index, columns) in colors.iter().enumerate() {
+            let Some(columns) = columns else {
+                continue;
+            };
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            let _ = write!(out, "#{}", index);
+            push_runs(&mut out, columns);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Appends sixel characters for `columns`, run-length encoding repeats.
+fn push_runs(out: &mut String, columns: &[u8]) {
+    let mut i = 0;
+    while i < columns.len() {
+        let bits = columns[i];
+        let run = columns[i..].iter().take_while(|&&b| b == bits).count();
+        let c = (b'?' + bits) as char;
+        if run > 3 {
+            let _ = write!(out, "!{}{}", run, c);
+        } else {
+            (0..run).for_each(|_| out.push(c));
+        }
+        i += run;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn test_sixel() {
+        // Red on top of a transparent row, then a green pixel in the second band.
+        let mut image = RgbaImage::new(5, 7);
+        for x in 0..5 {
+            image.put_pixel(x, 0, Rgba([255, 0, 0, 255]));
+        }
+        image.put_pixel(2, 6, Rgba([0, 255, 0, 255]));
+        let sixel = sixel(&image);
+        assert!(sixel.starts_with("\x1bPq\"1;1;5;7#0;2;0;0;0"));
+        assert!(sixel.contains("#180;2;100;0;0"));
+        assert!(sixel.ends_with("#180!5@-#30??@??-\x1b\\"));
+    }
+
+    #[test]
+    fn test_kitty_chunks() {
+        let image = RgbaImage::from_pixel(40, 40, Rgba([0, 0, 255, 255]));
+        let kitty = kitty(&image);
+        assert!(kitty.starts_with("\x1b_Ga=T,f=32,s=40,v=40,m=1;"));
+        assert_eq!(kitty.matches("\x1b_G").count(), 3);
+        assert!(kitty.contains("\x1b_Gm=0;"));
+    }
+}
@@ -0,0 +1,42 @@
+//! Replays recorded Custom Search responses, see `search_fixtures`.
+
+use std::path::Path;
+
+use search_fixtures::Api;
+use wiremock::MockServer;
+
+use crate::{query_params, GoogleSearchClient, SearchRequest, DEFAULT_BASE_URL};
+
+const API: Api = Api {
+    url: DEFAULT_BASE_URL,
+    credentials: &[
+        ("key", "GOOGLE_SEARCH_API_KEY", "test-key"),
+        ("cx", "GOOGLE_SEARCH_ENGINE_ID", "test-engine"),
+    ],
+};
+
+/// A stub server answering `request` with the fixture `name` in `dir`, and a client pointed at it.
+pub async fn replay_in(
+    dir: impl AsRef<Path>,
+    name: &str,
+    request: &SearchRequest,
+) -> (MockServer, GoogleSearchClient) {
+    let params = query_params(request.clone());
+    let server = search_fixtures::replay(dir.as_ref(), name, &API, &params).await;
+    let client = GoogleSearchClient::builder("test-key", "test-engine")
+        .base_url(server.uri())
+        .build()
+        .unwrap();
+    (server, client)
+}
+
+/// Replays the fixture `name` of this crate.
+#[cfg(test)]
+pub async fn replay(name: &str, request: &SearchRequest) -> (MockServer, GoogleSearchClient) {
+    replay_in(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures"),
+        name,
+        request,
+    )
+    .await
+}
@@ -0,0 +1,316 @@
+use std::time::Duration;
+
+use futures::{Stream, TryStreamExt};
+
+use crate::{query_params, YoutubeError, YoutubeItem, YoutubeSearchRequest, YoutubeSearchResponse};
+
+pub const DEFAULT_BASE_URL: &str = "https://youtube.googleapis.com/youtube/v3/search";
+pub const DEFAULT_USER_AGENT: &str = concat!("search-youtube/", env!("CARGO_PKG_VERSION"));
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// The largest `max_results` the API accepts.
+pub const MAX_PAGE_SIZE: usize = 50;
+
+/// A YouTube Data API client, cheap to clone and meant to be reused so connections are pooled.
+#[derive(Debug, Clone)]
+pub struct YoutubeClient {
+    api_key: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl YoutubeClient {
+    /// A client with the default base URL, timeout and user agent.
+    pub fn new(api_key: impl Into<String>) -> Result<Self, YoutubeError> {
+        Self::builder(api_key).build()
+    }
+
+    /// Reads `YOUTUBE_API_KEY`.
+    pub fn from_env() -> Result<Self, YoutubeError> {
+        Self::new(std::env::var("YOUTUBE_API_KEY").map_err(|_| YoutubeError::MissingApiKey)?)
+    }
+
+    pub fn builder(api_key: impl Into<String>) -> YoutubeClientBuilder {
+        YoutubeClientBuilder {
+            api_key: api_key.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            client: None,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub async fn search(
+        &self,
+        request: YoutubeSearchRequest,
+    ) -> Result<YoutubeSearchResponse, YoutubeError> {
+        let mut params = vec![("key", self.api_key.clone())];
+        params.extend(query_params(request));
+
+        let response = self
+            .client
+            .get(&self.base_url)
+            .query(&params)
+            .send()
+            .await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(YoutubeError::from_response(status.as_u16(), &text));
+        }
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// The pages of results for `request`, following `nextPageToken` until `total` results,
+    /// the last page or `pageInfo.totalResults` is reached.
+    ///
+    /// Paging starts at `request.page_token`, its `max_results` is ignored.
+    pub fn pages(
+        &self,
+        request: YoutubeSearchRequest,
+        total: usize,
+    ) -> impl Stream<Item = Result<YoutubeSearchResponse, YoutubeError>> + '_ {
+        let page_token = request.page_token.clone();
+        futures::stream::try_unfold(Some((page_token, 0)), move |next| {
+            let request = request.clone();
+            async move {
+                let Some((page_token, fetched)) = next else {
+                    return Ok(None);
+                };
+                let max_results = total.saturating_sub(fetched).min(MAX_PAGE_SIZE);
+                if max_results == 0 {
+                    return Ok(None);
+                }
+                let response = self
+                    .search(YoutubeSearchRequest {
+                        page_token,
+                        max_results: Some(max_results as i64),
+                        ..request
+                    })
+                    .await?;
+                let fetched = fetched + response.items.len();
+                let available = response
+                    .page_info
+                    .total_results
+                    .map_or(usize::MAX, |total_results| total_results.max(0) as usize);
+                let next = match &response.next_page_token {
+                    Some(token) if !response.items.is_empty() && fetched < available => {
+                        Some((Some(token.clone()), fetched))
+                    }
+                    _ => None,
+                };
+                Ok(Some((response, next)))
+            }
+        })
+    }
+
+    /// Up to `total` results for `request`, from as many pages as needed.
+    pub async fn search_all(
+        &self,
+        request: YoutubeSearchRequest,
+        total: usize,
+    ) -> Result<Vec<YoutubeItem>, YoutubeError> {
+        let mut items: Vec<YoutubeItem> = self
+            .pages(request, total)
+            .map_ok(|response| response.items)
+            .try_concat()
+            .await?;
+        items.truncate(total);
+        Ok(items)
+    }
+}
+
+pub struct YoutubeClientBuilder {
+    api_key: String,
+    base_url: String,
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+    client: Option<reqwest::Client>,
+}
+
+impl YoutubeClientBuilder {
+    /// Where to send requests instead of the search endpoint, e.g. a local mock server.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// The timeout of a whole request, 30 seconds by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Shares an existing client, whose own timeouts and user agent are used instead.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn build(self) -> Result<YoutubeClient, YoutubeError> {
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let mut builder = reqwest::Client::builder()
+                    .timeout(self.timeout)
+                    .user_agent(self.user_agent);
+                if let Some(connect_timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(connect_timeout);
+                }
+                builder.build()?
+            }
+        };
+        Ok(YoutubeClient {
+            api_key: self.api_key,
+            base_url: self.base_url,
+            client,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    /// A page of `count` videos numbered from `first`, linking to `next` if given.
+    fn page(first: usize, count: usize, total: i64, next: Option<&str>) -> serde_json::Value {
+        let items = (first..first + count)
+            .map(|i| {
+                serde_json::json!({
+                    "kind": "youtube#searchResult",
+                    "etag": format!("etag-{}", i),
+                    "id": { "kind": "youtube#video", "videoId": format!("video{}", i) },
+                    "snippet": {
+                        "publishedAt": "2024-01-01T00:00:00Z",
+                        "title": format!("Video {}", i),
+                        "description": "",
+                        "channelTitle": "Channel",
+                        "publishTime": "2024-01-01T00:00:00Z"
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "kind": "youtube#searchListResponse",
+            "etag": "etag",
+            "nextPageToken": next,
+            "pageInfo": { "totalResults": total, "resultsPerPage": count },
+            "items": items
+        })
+    }
+
+    #[tokio::test]
+    async fn test_search_all_follows_page_tokens() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("maxResults", "50"))
+            .and(query_param_is_missing("pageToken"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page(
+                0,
+                50,
+                1000,
+                Some("CDIQAA"),
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(query_param("maxResults", "20"))
+            .and(query_param("pageToken", "CDIQAA"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page(
+                50,
+                20,
+                1000,
+                Some("CEYQAA"),
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = YoutubeClient::builder("secret")
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let request = YoutubeSearchRequest {
+            q: Some("rust".to_string()),
+            ..Default::default()
+        };
+        let items = client.search_all(request, 70).await.unwrap();
+        assert_eq!(items.len(), 70);
+        assert_eq!(items[69].id.video_id.as_deref(), Some("video69"));
+    }
+
+    #[tokio::test]
+    async fn test_pages_stop_at_total_results() {
+        let server = MockServer::start().await;
+        // YouTube keeps handing out tokens past the results it reported.
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page(0, 3, 3, Some("CAMQAA"))))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = YoutubeClient::builder("secret")
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let pages: Vec<_> = client
+            .pages(YoutubeSearchRequest::default(), 100)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].page_info.total_results, Some(3));
+        assert_eq!(pages[0].next_page_token.as_deref(), Some("CAMQAA"));
+    }
+
+    #[tokio::test]
+    async fn test_search_with_custom_client() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("key", "secret"))
+            .and(query_param("q", "rust"))
+            .and(header("user-agent", "search-tests/1.0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "kind": "youtube#searchListResponse",
+                "etag": "abc",
+                "pageInfo": { "totalResults": 0, "resultsPerPage": 5 },
+                "items": []
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = YoutubeClient::builder("secret")
+            .base_url(server.uri())
+            .timeout(Duration::from_secs(5))
+            .user_agent("search-tests/1.0")
+            .build()
+            .unwrap();
+        let request = YoutubeSearchRequest {
+            q: Some("rust".to_string()),
+            ..Default::default()
+        };
+        let response = client.search(request).await.unwrap();
+        assert_eq!(response.kind, "youtube#searchListResponse");
+        assert!(response.items.is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+/// Everything that can go wrong searching YouTube.
+///
+/// The reasons callers usually react to, by backing off or switching keys, have their own
+/// variants; any other error response is `Api`.
+#[derive(Debug)]
+pub enum YoutubeError {
+    /// `YOUTUBE_API_KEY` isn't set.
+    MissingApiKey,
+    /// YouTube couldn't be reached or the response couldn't be read.
+    Http(reqwest::Error),
+    /// The project's daily quota is used up, `quotaExceeded`.
+    QuotaExceeded(ApiError),
+    /// The API key is wrong, expired or restricted, `keyInvalid`.
+    KeyInvalid(ApiError),
+    /// The filters can't be combined, e.g. video filters without `--type video`, `invalidSearchFilter`.
+    InvalidSearchFilter(ApiError),
+    /// Any other error response.
+    Api(ApiError),
+    /// The response isn't a search response.
+    Decode(serde_json::Error),
+}
+
+/// The `error` object of a YouTube Data API error response, see
+/// <https://developers.google.com/youtube/v3/docs/errors>.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiError {
+    pub code: u16,
+    #[serde(default)]
+    pub message: String,
+    /// The canonical status, e.g. `INVALID_ARGUMENT`.
+    pub status: Option<String>,
+    #[serde(default)]
+    pub errors: Vec<ApiErrorDetail>,
+    /// Newer errors carry their reason here instead, e.g. `API_KEY_INVALID`.
+    #[serde(default)]
+    pub details: Vec<ApiErrorDetail>,
+}
+
+/// An entry of `errors` or `details`, shaped like the Custom Search one.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiErrorDetail {
+    #[serde(default)]
+    pub domain: String,
+    /// E.g. `quotaExceeded`, `keyInvalid` or `invalidSearchFilter`.
+    #[serde(default)]
+    pub reason: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl ApiError {
+    /// Reads the `error` object YouTube answers with; anything else, like a gateway's HTML
+    /// page, is kept whole as the message.
+    pub fn parse(status: u16, body: &str) -> Self {
+        serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|mut value| serde_json::from_value(value["error"].take()).ok())
+            .unwrap_or_else(|| ApiError {
+                code: status,
+                message: body.trim().to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// The reasons of both `errors` and `details`.
+    pub fn reasons(&self) -> impl Iterator<Item = &str> {
+        self.errors
+            .iter()
+            .chain(&self.details)
+            .map(|detail| detail.reason.as_str())
+    }
+
+    pub fn has_reason(&self, reason: &str) -> bool {
+        self.reasons().any(|r| r == reason)
+    }
+}
+
+impl YoutubeError {
+    /// Classifies an error response by its reasons.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let error = ApiError::parse(status, body);
+        if error.has_reason("quotaExceeded") || error.has_reason("dailyLimitExceeded") {
+            YoutubeError::QuotaExceeded(error)
+        } else if error.has_reason("keyInvalid") || error.has_reason("API_KEY_INVALID") {
+            YoutubeError::KeyInvalid(error)
+        } else if error.has_reason("invalidSearchFilter") {
+            YoutubeError::InvalidSearchFilter(error)
+        } else {
+            YoutubeError::Api(error)
+        }
+    }
+
+    /// The error response, if YouTube answered with one.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            YoutubeError::QuotaExceeded(e)
+            | YoutubeError::KeyInvalid(e)
+            | YoutubeError::InvalidSearchFilter(e)
+            | YoutubeError::Api(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for YoutubeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            YoutubeError::MissingApiKey => {
+                write!(f, "YOUTUBE_API_KEY environment variable not set")
+            }
+            YoutubeError::Http(e) => write!(f, "could not reach YouTube: {}", e),
+            YoutubeError::QuotaExceeded(e) => {
+                write!(f, "YouTube quota exceeded ({}): {}", e.code, e.message)
+            }
+            YoutubeError::KeyInvalid(e) => {
+                write!(f, "YouTube API key invalid ({}): {}", e.code, e.message)
+            }
+            YoutubeError::InvalidSearchFilter(e) => {
+                write!(
+                    f,
+                    "invalid YouTube search filter ({}): {}",
+                    e.code, e.message
+                )
+            }
+            YoutubeError::Api(e) => write!(f, "YouTube API error ({}): {}", e.code, e.message),
+            YoutubeError::Decode(e) => write!(f, "could not decode the YouTube response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for YoutubeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            YoutubeError::Http(e) => Some(e),
+            YoutubeError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for YoutubeError {
+    fn from(e: reqwest::Error) -> Self {
+        YoutubeError::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for YoutubeError {
+    fn from(e: serde_json::Error) -> Self {
+        YoutubeError::Decode(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_response() {
+        let quota = r#"{"error": {"code": 403, "message": "The request cannot be completed because you have exceeded your quota.", "errors": [{"message": "The request cannot be completed because you have exceeded your quota.", "domain": "youtube.quota", "reason": "quotaExceeded"}]}}"#;
+        let e = YoutubeError::from_response(403, quota);
+        assert!(matches!(e, YoutubeError::QuotaExceeded(_)));
+        assert_eq!(
+            e.to_string(),
+            "YouTube quota exceeded (403): The request cannot be completed because you have exceeded your quota."
+        );
+
+        let key = r#"{"error": {"code": 400, "message": "API key not valid. Please pass a valid API key.", "errors": [{"message": "API key not valid. Please pass a valid API key.", "domain": "global", "reason": "badRequest"}], "status": "INVALID_ARGUMENT", "details": [{"@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "API_KEY_INVALID", "domain": "googleapis.com"}]}}"#;
+        assert!(matches!(
+            YoutubeError::from_response(400, key),
+            YoutubeError::KeyInvalid(_)
+        ));
+
+        let filter = r#"{"error": {"code": 400, "message": "Request contains an invalid argument.", "errors": [{"domain": "youtube.search", "reason": "invalidSearchFilter"}]}}"#;
+        assert!(matches!(
+            YoutubeError::from_response(400, filter),
+            YoutubeError::InvalidSearchFilter(_)
+        ));
+
+        let e = YoutubeError::from_response(503, "Service Unavailable");
+        assert_eq!(e.api_error().map(|e| e.code), Some(503));
+        assert_eq!(
+            e.to_string(),
+            "YouTube API error (503): Service Unavailable"
+        );
+    }
+}
@@ -3,10 +3,23 @@ use std::fmt::Display;
 use clap::{Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
 
+mod client;
+mod error;
+#[cfg(any(test, feature = "replay"))]
+pub mod replay;
+
+pub use client::{YoutubeClient, YoutubeClientBuilder, DEFAULT_BASE_URL, MAX_PAGE_SIZE};
+pub use error::{ApiError, ApiErrorDetail, YoutubeError};
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct YoutubeSearchResponse {
     pub kind: String,
     pub etag: String,
+    /// The `page_token` of the next page, missing on the last one.
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+    #[serde(rename = "prevPageToken")]
+    pub prev_page_token: Option<String>,
     #[serde(rename = "pageInfo")]
     pub page_info: PageInfo,
     pub items: Vec<YoutubeItem>,
@@ -14,7 +27,10 @@ pub struct YoutubeSearchResponse {
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PageInfo {
+    /// An approximation, and never more than what the API will actually page through.
+    #[serde(rename = "totalResults")]
     pub total_results: Option<i64>,
+    #[serde(rename = "resultsPerPage")]
     pub results_per_page: Option<i64>,
 }
 
@@ -47,7 +63,7 @@ pub struct YoutubeSnippet {
     pub publish_time: String,
 }
 
-#[derive(Debug, Serialize, Clone, Parser)]
+#[derive(Debug, Default, Serialize, Clone, Parser)]
 pub struct YoutubeSearchRequest {
     #[clap(
         short,
@@ -698,13 +714,19 @@ impl Display for EventType {
     }
 }
 
+/// Searches YouTube once with the key in `YOUTUBE_API_KEY`.
+///
+/// Fetches a single page of at most `max_results`; `YoutubeClient::search_all` follows the
+/// page tokens for more.
 pub async fn search_youtube(
     request: YoutubeSearchRequest,
-) -> Result<YoutubeSearchResponse, Box<dyn std::error::Error>> {
-    let youtube_api_key = std::env::var("YOUTUBE_API_KEY").expect("YOUTUBE_API_KEY not set");
-    let url = "https://youtube.googleapis.com/youtube/v3/search";
-    let client = reqwest::Client::new();
-    let mut params: Vec<(&str, String)> = vec![("key", youtube_api_key)];
+) -> Result<YoutubeSearchResponse, YoutubeError> {
+    YoutubeClient::from_env()?.search(request).await
+}
+
+/// The query parameters of `request`, except for the key.
+fn query_params(request: YoutubeSearchRequest) -> Vec<(&'static str, String)> {
+    let mut params: Vec<(&str, String)> = vec![];
     if let Some(part) = request.part {
         params.push(("part", part));
     }
@@ -792,12 +814,41 @@ pub async fn search_youtube(
     if let Some(video_type) = request.video_type {
         params.push(("videoType", video_type.to_string()));
     }
-    let response = client
-        .get(url)
-        .query(&params)
-        .send()
-        .await?;
-    let text = response.text().await?;
-    let youtube_response: YoutubeSearchResponse = serde_json::from_str(&text)?;
-    Ok(youtube_response)
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_youtube() {
+        let request = YoutubeSearchRequest {
+            q: Some("rust programming".to_string()),
+            part: Some("snippet".to_string()),
+            max_results: Some(3),
+            order: Some(Order::Relevance),
+            ..Default::default()
+        };
+        let (_server, client) = replay::replay("rust_programming", &request).await;
+        let response = client.search(request).await.unwrap();
+        assert_eq!(response.kind, "youtube#searchListResponse");
+        assert_eq!(response.items.len(), 3);
+        assert_eq!(response.items[0].id.video_id.as_deref(), Some("tLv5hD4SVjE"));
+        assert_eq!(
+            response.items[2].id.channel_id.as_deref(),
+            Some("UCSp-OaMpsO8K0KkOqyBl7_w")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_youtube_quota_exceeded() {
+        let request = YoutubeSearchRequest {
+            q: Some("rust".to_string()),
+            ..Default::default()
+        };
+        let (_server, client) = replay::replay("quota_exceeded", &request).await;
+        let e = client.search(request).await.unwrap_err();
+        assert!(matches!(e, YoutubeError::QuotaExceeded(_)));
+    }
 }
@@ -1,11 +1,52 @@
 use clap::Parser;
-use search_youtube::{search_youtube, YoutubeSearchRequest};
+use search_youtube::{YoutubeClient, YoutubeError, YoutubeItem, YoutubeSearchRequest};
+
+/// The flags that keep their short form. clap derives the request's short flags from the first
+/// letter of each field, so `-p` would be part, page token and both published dates.
+const SHORT_FLAGS: &[&str] = &[
+    "event_type",
+    "limit",
+    "max_results",
+    "q",
+    "safe_search",
+    "type_",
+];
+
+#[derive(Debug, Parser)]
+#[clap(mut_args(|arg| if SHORT_FLAGS.contains(&arg.get_id().as_str()) {
+    arg
+} else {
+    arg.short(None)
+}))]
+struct Cli {
+    #[clap(flatten)]
+    request: YoutubeSearchRequest,
+    #[clap(
+        short = 'n',
+        long,
+        help = "Fetch up to this many results, following the pages 50 at a time"
+    )]
+    limit: Option<usize>,
+}
+
+async fn run(cli: Cli) -> Result<Vec<YoutubeItem>, YoutubeError> {
+    let client = YoutubeClient::from_env()?;
+    match cli.limit {
+        Some(limit) => client.search_all(cli.request, limit).await,
+        None => Ok(client.search(cli.request).await?.items),
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    let youtube_search_request: YoutubeSearchRequest = YoutubeSearchRequest::parse();
-    let youtube_search_response = search_youtube(youtube_search_request).await.unwrap();
-    for item in youtube_search_response.items {
+    let items = match run(Cli::parse()).await {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    for item in items {
         println!("Title: {}", item.snippet.title);
         println!("Description: {}", item.snippet.description);
         println!("Published at: {}", item.snippet.published_at);
@@ -21,3 +62,31 @@ async fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from([
+            "youtube",
+            "-q",
+            "rust programming",
+            "-m",
+            "5",
+            "--page-token",
+            "CAUQAA",
+            "-n",
+            "20",
+        ]);
+        assert_eq!(cli.request.q.as_deref(), Some("rust programming"));
+        assert_eq!(cli.request.max_results, Some(5));
+        assert_eq!(cli.request.page_token.as_deref(), Some("CAUQAA"));
+        assert_eq!(cli.limit, Some(20));
+        assert!(Cli::try_parse_from(["youtube", "-p", "snippet"]).is_err());
+    }
+}
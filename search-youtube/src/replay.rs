@@ -0,0 +1,39 @@
+//! Replays recorded YouTube Data API responses, see `search_fixtures`.
+
+use std::path::Path;
+
+use search_fixtures::Api;
+use wiremock::MockServer;
+
+use crate::{query_params, YoutubeClient, YoutubeSearchRequest, DEFAULT_BASE_URL};
+
+const API: Api = Api {
+    url: DEFAULT_BASE_URL,
+    credentials: &[("key", "YOUTUBE_API_KEY", "test-key")],
+};
+
+/// A stub server answering `request` with the fixture `name` in `dir`, and a client pointed at it.
+pub async fn replay_in(
+    dir: impl AsRef<Path>,
+    name: &str,
+    request: &YoutubeSearchRequest,
+) -> (MockServer, YoutubeClient) {
+    let params = query_params(request.clone());
+    let server = search_fixtures::replay(dir.as_ref(), name, &API, &params).await;
+    let client = YoutubeClient::builder("test-key")
+        .base_url(server.uri())
+        .build()
+        .unwrap();
+    (server, client)
+}
+
+/// Replays the fixture `name` of this crate.
+#[cfg(test)]
+pub async fn replay(name: &str, request: &YoutubeSearchRequest) -> (MockServer, YoutubeClient) {
+    replay_in(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures"),
+        name,
+        request,
+    )
+    .await
+}
@@ -0,0 +1,36 @@
+CREATE TABLE queries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    text TEXT NOT NULL,
+    -- SearchOptions as JSON
+    parameters TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    query_id INTEGER NOT NULL REFERENCES queries (id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    -- 'ok', 'error', or 'cached' when answered from an earlier run (see cached_run_id)
+    status TEXT NOT NULL,
+    error TEXT,
+    started_at TEXT NOT NULL,
+    elapsed_ms INTEGER NOT NULL
+);
+
+CREATE INDEX runs_query_id ON runs (query_id);
+
+CREATE TABLE results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL REFERENCES runs (id) ON DELETE CASCADE,
+    rank INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT,
+    snippet TEXT NOT NULL,
+    author TEXT,
+    published_at TEXT,
+    score REAL,
+    -- provider payload as JSON
+    raw TEXT NOT NULL
+);
+
+CREATE INDEX results_run_id ON results (run_id);
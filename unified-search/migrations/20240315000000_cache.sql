@@ -0,0 +1,6 @@
+ALTER TABLE runs ADD COLUMN cache_key TEXT;
+-- Set when the run was answered from an earlier run instead of the network, its status is
+-- then 'cached'.
+ALTER TABLE runs ADD COLUMN cached_run_id INTEGER REFERENCES runs (id);
+
+CREATE INDEX runs_cache_key ON runs (source, cache_key, status);
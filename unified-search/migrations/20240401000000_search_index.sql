@@ -0,0 +1,24 @@
+CREATE VIRTUAL TABLE queries_fts USING fts5 (text, content = 'queries', content_rowid = 'id');
+
+CREATE TRIGGER queries_fts_insert AFTER INSERT ON queries BEGIN
+    INSERT INTO queries_fts (rowid, text) VALUES (new.id, new.text);
+END;
+
+CREATE TRIGGER queries_fts_delete AFTER DELETE ON queries BEGIN
+    INSERT INTO queries_fts (queries_fts, rowid, text) VALUES ('delete', old.id, old.text);
+END;
+
+CREATE VIRTUAL TABLE results_fts USING fts5 (title, snippet, content = 'results', content_rowid = 'id');
+
+CREATE TRIGGER results_fts_insert AFTER INSERT ON results BEGIN
+    INSERT INTO results_fts (rowid, title, snippet) VALUES (new.id, new.title, new.snippet);
+END;
+
+CREATE TRIGGER results_fts_delete AFTER DELETE ON results BEGIN
+    INSERT INTO results_fts (results_fts, rowid, title, snippet)
+    VALUES ('delete', old.id, old.title, old.snippet);
+END;
+
+-- Index everything recorded before this migration.
+INSERT INTO queries_fts (queries_fts) VALUES ('rebuild');
+INSERT INTO results_fts (results_fts) VALUES ('rebuild');
@@ -0,0 +1,2 @@
+-- The generated answer's model, finish reason, usage and safety ratings, for LLM results.
+ALTER TABLE results ADD COLUMN answer TEXT;
@@ -0,0 +1,3 @@
+-- When the result last changed and its programming language, for sources that have them.
+ALTER TABLE results ADD COLUMN updated_at TEXT;
+ALTER TABLE results ADD COLUMN language TEXT;
@@ -0,0 +1,153 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::result::UnifiedResult;
+
+/// A generated answer, in the same shape for every LLM source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Answer {
+    pub model: String,
+    pub text: String,
+    pub finish_reason: Option<String>,
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub safety_ratings: Vec<SafetyRating>,
+    /// The untouched provider response.
+    pub raw: serde_json::Value,
+}
+
+/// What a result keeps of an answer besides its text and the provider payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnswerDetails {
+    pub model: String,
+    pub finish_reason: Option<String>,
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyRating {
+    /// e.g. `HARM_CATEGORY_HARASSMENT`
+    pub category: String,
+    /// e.g. `NEGLIGIBLE`, `LOW`, `MEDIUM`, `HIGH`
+    pub probability: String,
+    pub blocked: bool,
+}
+
+impl Answer {
+    /// A result with the provider payload as `raw` and the rest of the answer as `answer`.
+    pub fn into_result(self, source: &str, query: &str) -> UnifiedResult {
+        let details = AnswerDetails {
+            model: self.model,
+            finish_reason: self.finish_reason,
+            usage: self.usage,
+            safety_ratings: self.safety_ratings,
+        };
+        let mut result = UnifiedResult::from_llm_response(
+            source,
+            Some(&details.model),
+            query,
+            self.text,
+            self.raw,
+        );
+        result.answer = Some(details);
+        result
+    }
+
+    /// Recovers the answer from a result built with `into_result`, e.g. one loaded from the cache;
+    /// `None` for every other result.
+    pub fn from_result(result: &UnifiedResult) -> Option<Self> {
+        let details = result.answer.clone()?;
+        Some(Answer {
+            model: details.model,
+            text: result.snippet.clone(),
+            finish_reason: details.finish_reason,
+            usage: details.usage,
+            safety_ratings: details.safety_ratings,
+            raw: result.raw.clone(),
+        })
+    }
+
+    /// The model, finish reason, token usage and notable safety ratings, for display under the text.
+    pub fn details(&self) -> String {
+        let mut details = format!("[{}", self.model);
+        if let Some(finish_reason) = &self.finish_reason {
+            details += &format!(" | finished: {}", finish_reason.to_lowercase());
+        }
+        if let Some(usage) = &self.usage {
+            details += &format!(
+                " | tokens: {} prompt + {} completion = {}",
+                usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+            );
+        }
+        details += "]";
+        // Negligible ratings are the norm, only call out the rest.
+        let flagged = self
+            .safety_ratings
+            .iter()
+            .filter(|rating| rating.blocked || rating.probability != "NEGLIGIBLE")
+            .map(|rating| {
+                let category = rating.category.trim_start_matches("HARM_CATEGORY_");
+                let blocked = if rating.blocked { ", blocked" } else { "" };
+                format!("{} {}{}", category, rating.probability, blocked).to_lowercase()
+            })
+            .collect::<Vec<_>>();
+        if !flagged.is_empty() {
+            details += &format!("\n[safety: {}]", flagged.join(", "));
+        }
+        details
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.text.trim_end())?;
+        writeln!(f)?;
+        write!(f, "{}", self.details())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let answer = Answer {
+            model: "gemini-pro".to_string(),
+            text: "Paris.\n".to_string(),
+            finish_reason: Some("STOP".to_string()),
+            usage: Some(Usage {
+                prompt_tokens: 7,
+                completion_tokens: 2,
+                total_tokens: 9,
+            }),
+            safety_ratings: vec![
+                SafetyRating {
+                    category: "HARM_CATEGORY_HARASSMENT".to_string(),
+                    probability: "NEGLIGIBLE".to_string(),
+                    blocked: false,
+                },
+                SafetyRating {
+                    category: "HARM_CATEGORY_HATE_SPEECH".to_string(),
+                    probability: "LOW".to_string(),
+                    blocked: false,
+                },
+            ],
+            raw: serde_json::Value::Null,
+        };
+        assert_eq!(
+            answer.to_string(),
+            "Paris.\n\n[gemini-pro | finished: stop | tokens: 7 prompt + 2 completion = 9]\n[safety: hate_speech low]"
+        );
+    }
+}
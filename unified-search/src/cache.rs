@@ -0,0 +1,234 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::fanout::{self, SourceOutcome};
+use crate::provider::{self, SearchOptions, SearchProvider};
+use crate::storage::Storage;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Serve fresh cached results, go to the network otherwise.
+    #[default]
+    Default,
+    /// Always go to the network.
+    Refresh,
+    /// Never go to the network; stale results are served as-is.
+    Offline,
+}
+
+/// Results of a cache-first search.
+pub struct CachedSearch {
+    /// One outcome per provider, in provider order.
+    pub outcomes: Vec<SourceOutcome>,
+    /// Network searches for providers whose stale results were served.
+    pub revalidation: Option<JoinHandle<Vec<SourceOutcome>>>,
+}
+
+pub struct Cache {
+    storage: Storage,
+    mode: CacheMode,
+    stale_while_revalidate: bool,
+}
+
+impl Cache {
+    pub fn new(storage: Storage, mode: CacheMode, stale_while_revalidate: bool) -> Self {
+        Cache {
+            storage,
+            mode,
+            stale_while_revalidate,
+        }
+    }
+
+    /// Answers every provider from the database when its TTL allows, otherwise from the network.
+    pub async fn search(
+        &self,
+        providers: &[Arc<dyn SearchProvider>],
+        query: &str,
+        options: &SearchOptions,
+    ) -> CachedSearch {
+        let mut outcomes: Vec<Option<SourceOutcome>> = Vec::with_capacity(providers.len());
+        let mut misses = vec![];
+        let mut stale = vec![];
+        for provider in providers {
+            let cache_key = provider::cache_key(provider.as_ref(), query, options);
+            let run = match self.mode {
+                CacheMode::Refresh => None,
+                _ => self
+                    .storage
+                    .latest_run(provider.name(), &cache_key)
+                    .await
+                    .unwrap_or_default(),
+            };
+            let Some(run) = run else {
+                outcomes.push(match self.mode {
+                    CacheMode::Offline => Some(SourceOutcome {
+                        source: provider.name().to_string(),
+                        cache_key,
+                        cached_run: None,
+                        results: Err("no cached results and running offline".to_string()),
+                        started_at: Utc::now(),
+                        elapsed: Duration::ZERO,
+                    }),
+                    _ => {
+                        misses.push(Arc::clone(provider));
+                        None
+                    }
+                });
+                continue;
+            };
+            let age = (Utc::now() - run.started_at).to_std().unwrap_or_default();
+            let is_fresh = age < provider.cache_ttl();
+            if !is_fresh && self.mode != CacheMode::Offline {
+                if self.stale_while_revalidate {
+                    stale.push(Arc::clone(provider));
+                } else {
+                    misses.push(Arc::clone(provider));
+                    outcomes.push(None);
+                    continue;
+                }
+            }
+            outcomes.push(Some(SourceOutcome {
+                source: provider.name().to_string(),
+                cache_key,
+                cached_run: Some(run.id),
+                results: Ok(run.results),
+                started_at: Utc::now(),
+                elapsed: Duration::ZERO,
+            }));
+        }
+
+        let revalidation = (!stale.is_empty()).then(|| {
+            let query = query.to_string();
+            // Refreshed answers are stored for next time, not streamed over the stale ones.
+            let options = SearchOptions {
+                tokens: None,
+                ..options.clone()
+            };
+            tokio::spawn(async move { fanout::search_all(&stale, &query, &options).await })
+        });
+        let mut fetched = fanout::search_all(&misses, query, options)
+            .await
+            .into_iter();
+        let outcomes = outcomes
+            .into_iter()
+            .map(|outcome| outcome.or_else(|| fetched.next()))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        CachedSearch {
+            outcomes,
+            revalidation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::provider::{Capabilities, Error};
+    use crate::result::UnifiedResult;
+
+    struct Counting {
+        calls: AtomicUsize,
+        ttl: Duration,
+    }
+
+    #[async_trait]
+    impl SearchProvider for Counting {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        fn cache_ttl(&self) -> Duration {
+            self.ttl
+        }
+
+        async fn search(
+            &self,
+            query: &str,
+            _options: &SearchOptions,
+        ) -> Result<Vec<UnifiedResult>, Error> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(vec![UnifiedResult::from_llm_response(
+                self.name(),
+                None,
+                query,
+                format!("call {}", call),
+                serde_json::Value::Null,
+            )])
+        }
+    }
+
+    async fn run(cache: &Cache, providers: &[Arc<dyn SearchProvider>]) -> Vec<SourceOutcome> {
+        let options = SearchOptions::default();
+        let storage = &cache.storage;
+        let query_id = storage.record_query("rust", &options).await.unwrap();
+        let search = cache.search(providers, "rust", &options).await;
+        for outcome in &search.outcomes {
+            storage.record_run(query_id, outcome).await.unwrap();
+        }
+        if let Some(revalidation) = search.revalidation {
+            for outcome in &revalidation.await.unwrap() {
+                storage.record_run(query_id, outcome).await.unwrap();
+            }
+        }
+        search.outcomes
+    }
+
+    fn snippet(outcomes: &[SourceOutcome]) -> &str {
+        &outcomes[0].results.as_ref().unwrap()[0].snippet
+    }
+
+    #[tokio::test]
+    async fn test_cache_modes() {
+        let storage = Storage::in_memory().await.unwrap();
+        let provider = Arc::new(Counting {
+            calls: AtomicUsize::new(0),
+            ttl: Duration::from_secs(3600),
+        });
+        let providers: Vec<Arc<dyn SearchProvider>> = vec![provider.clone()];
+
+        let offline = Cache::new(storage.clone(), CacheMode::Offline, false);
+        assert!(run(&offline, &providers).await[0].results.is_err());
+
+        let cache = Cache::new(storage.clone(), CacheMode::Default, false);
+        assert_eq!(snippet(&run(&cache, &providers).await), "call 1");
+        let outcomes = run(&cache, &providers).await;
+        assert_eq!(snippet(&outcomes), "call 1");
+        assert!(outcomes[0].cached_run.is_some());
+
+        let refresh = Cache::new(storage.clone(), CacheMode::Refresh, false);
+        assert_eq!(snippet(&run(&refresh, &providers).await), "call 2");
+        assert_eq!(snippet(&run(&offline, &providers).await), "call 2");
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_while_revalidate() {
+        let storage = Storage::in_memory().await.unwrap();
+        let provider = Arc::new(Counting {
+            calls: AtomicUsize::new(0),
+            ttl: Duration::ZERO,
+        });
+        let providers: Vec<Arc<dyn SearchProvider>> = vec![provider.clone()];
+
+        let cache = Cache::new(storage.clone(), CacheMode::Default, false);
+        assert_eq!(snippet(&run(&cache, &providers).await), "call 1");
+        assert_eq!(snippet(&run(&cache, &providers).await), "call 2");
+
+        let swr = Cache::new(storage.clone(), CacheMode::Default, true);
+        assert_eq!(snippet(&run(&swr, &providers).await), "call 2");
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(snippet(&run(&swr, &providers).await), "call 3");
+    }
+}
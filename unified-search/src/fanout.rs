@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::provider::{self, SearchOptions, SearchProvider};
+use crate::result::UnifiedResult;
+
+/// What a single provider produced during a fan-out search.
+#[derive(Debug)]
+pub struct SourceOutcome {
+    pub source: String,
+    pub cache_key: String,
+    /// The stored run these results were served from, `None` if they came from the network.
+    pub cached_run: Option<i64>,
+    pub results: Result<Vec<UnifiedResult>, String>,
+    pub started_at: DateTime<Utc>,
+    pub elapsed: Duration,
+}
+
+/// Runs every provider concurrently, each under its own timeout.
+///
+/// A provider that errors, times out or panics only affects its own outcome;
+/// the outcomes are returned in the same order as `providers`.
+pub async fn search_all(
+    providers: &[Arc<dyn SearchProvider>],
+    query: &str,
+    options: &SearchOptions,
+) -> Vec<SourceOutcome> {
+    let started_at = Utc::now();
+    let handles: Vec<_> = providers
+        .iter()
+        .map(|provider| {
+            let provider = Arc::clone(provider);
+            let query = query.to_string();
+            let options = options.clone();
+            let timeout = options.timeout.unwrap_or_else(|| provider.timeout());
+            tokio::spawn(async move {
+                let started = std::time::Instant::now();
+                let results =
+                    match tokio::time::timeout(timeout, provider.search(&query, &options)).await {
+                        Ok(Ok(results)) => Ok(results),
+                        Ok(Err(e)) => Err(e.to_string()),
+                        Err(_) => Err(format!("timed out after {}s", timeout.as_secs_f32())),
+                    };
+                (results, started.elapsed())
+            })
+        })
+        .collect();
+
+    let mut outcomes = Vec::with_capacity(handles.len());
+    for (provider, handle) in providers.iter().zip(handles) {
+        let (results, elapsed) = match handle.await {
+            Ok(outcome) => outcome,
+            Err(e) => (Err(format!("task failed: {}", e)), Duration::ZERO),
+        };
+        outcomes.push(SourceOutcome {
+            source: provider.name().to_string(),
+            cache_key: provider::cache_key(provider.as_ref(), query, options),
+            cached_run: None,
+            results,
+            started_at,
+            elapsed,
+        });
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::provider::{Capabilities, Error};
+
+    struct Fixed(&'static str);
+
+    #[async_trait]
+    impl SearchProvider for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        async fn search(
+            &self,
+            query: &str,
+            _options: &SearchOptions,
+        ) -> Result<Vec<UnifiedResult>, Error> {
+            match self.0 {
+                "failing" => Err("quota exceeded".into()),
+                "panicking" => panic!("unexpected response"),
+                "slow" => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(vec![])
+                }
+                _ => Ok(vec![UnifiedResult::from_llm_response(
+                    self.0,
+                    None,
+                    query,
+                    query.to_string(),
+                    serde_json::Value::Null,
+                )]),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_all_isolates_failures() {
+        let providers: Vec<Arc<dyn SearchProvider>> = vec![
+            Arc::new(Fixed("ok")),
+            Arc::new(Fixed("failing")),
+            Arc::new(Fixed("panicking")),
+            Arc::new(Fixed("slow")),
+        ];
+        let options = SearchOptions {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let outcomes = search_all(&providers, "rust", &options).await;
+        let sources: Vec<_> = outcomes.iter().map(|o| o.source.as_str()).collect();
+        assert_eq!(sources, ["ok", "failing", "panicking", "slow"]);
+        assert_eq!(outcomes[0].results.as_ref().unwrap().len(), 1);
+        assert_eq!(outcomes[1].results.as_ref().unwrap_err(), "quota exceeded");
+        assert!(outcomes[2]
+            .results
+            .as_ref()
+            .unwrap_err()
+            .starts_with("task failed"));
+        assert!(outcomes[3]
+            .results
+            .as_ref()
+            .unwrap_err()
+            .starts_with("timed out"));
+    }
+}
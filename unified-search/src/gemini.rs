@@ -1,10 +1,105 @@
-use google_generative_ai_rs::v1::{api::Client, gemini::{request::Request, Content, Part, Role}};
+use std::time::Duration;
 
-pub async fn search(query: &str) -> Result<(), Box<dyn std::error::Error>>{
-    let google_generative_api_key =
-        std::env::var("GOOGLE_GENERATIVE_API_KEY").expect("GOOGLE_GENERATIVE_API_KEY must be set");
-    let client: Client = Client::new(google_generative_api_key);
-    let txt_request = Request {
+use async_trait::async_trait;
+use futures::StreamExt;
+use google_generative_ai_rs::v1::gemini::{
+    request::{GenerationConfig, Request},
+    response::{GeminiResponse, SafetyRating},
+    Content, Part, ResponseType, Role,
+};
+
+use reqwest_streams::JsonStreamResponse;
+
+use crate::answer::{self, Answer, Usage};
+use crate::provider::{Capabilities, Error, SearchOptions, SearchProvider, TokenSender};
+use crate::result::UnifiedResult;
+
+pub const BASE_URL: &str = "https://generativelanguage.googleapis.com/v1";
+pub const DEFAULT_MODEL: &str = "gemini-pro";
+
+pub struct Gemini;
+
+#[async_trait]
+impl SearchProvider for Gemini {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            answer: true,
+            model_selection: true,
+            streaming: true,
+            ..Default::default()
+        }
+    }
+
+    fn is_configured(&self) -> bool {
+        std::env::var("GOOGLE_GENERATIVE_API_KEY").is_ok()
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(7 * 24 * 60 * 60)
+    }
+
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        serde_json::json!({
+            "model": model(options),
+            "request": request(query, options).ok(),
+        })
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error> {
+        let answer = match &options.tokens {
+            Some(tokens) => search_streamed(query, options, tokens).await?,
+            None => search(query, options).await?,
+        };
+        Ok(vec![answer.into_result(self.name(), query)])
+    }
+}
+
+/// The largest streamed chunk read; one with a long text part and its safety ratings is
+/// well above the 2048 bytes `Client::post` allows.
+const MAX_CHUNK_LEN: usize = 1024 * 1024;
+
+fn api_key() -> Result<String, Error> {
+    Ok(std::env::var("GOOGLE_GENERATIVE_API_KEY")
+        .map_err(|_| "GOOGLE_GENERATIVE_API_KEY must be set")?)
+}
+
+fn model(options: &SearchOptions) -> &str {
+    options.model.as_deref().unwrap_or(DEFAULT_MODEL)
+}
+
+/// The request for `query` with the sampling of `options`; Gemini has no repetition penalty,
+/// so that is refused rather than dropped.
+fn request(query: &str, options: &SearchOptions) -> Result<Request, Error> {
+    let sampling = &options.sampling;
+    if sampling.repetition_penalty.is_some() {
+        return Err("gemini does not support --repetition-penalty".into());
+    }
+    let generation_config = GenerationConfig {
+        temperature: sampling.temperature,
+        top_p: sampling.top_p,
+        top_k: sampling.top_k.map(|top_k| top_k as i32),
+        candidate_count: None,
+        max_output_tokens: sampling.max_tokens.map(|max_tokens| max_tokens as i32),
+        stop_sequences: Some(sampling.stop.clone()).filter(|stop| !stop.is_empty()),
+    };
+    let configured = generation_config.temperature.is_some()
+        || generation_config.top_p.is_some()
+        || generation_config.top_k.is_some()
+        || generation_config.max_output_tokens.is_some()
+        || generation_config.stop_sequences.is_some();
+    Ok(Request {
         contents: vec![Content {
             role: Role::User,
             parts: vec![Part {
@@ -16,9 +111,296 @@ pub async fn search(query: &str) -> Result<(), Box<dyn std::error::Error>>{
         }],
         tools: vec![],
         safety_settings: vec![],
-        generation_config: None,
+        generation_config: configured.then_some(generation_config),
+    })
+}
+
+/// Posts `query` to the `response_type` endpoint of the model in `options`.
+///
+/// Posted here rather than with the library's `Client::post`, which only knows its own models
+/// and hands back the parsed response, losing the body that is kept as the raw payload.
+async fn post(
+    query: &str,
+    options: &SearchOptions,
+    response_type: ResponseType,
+    timeout: Duration,
+) -> Result<reqwest::Response, Error> {
+    let request = request(query, options)?;
+    let url = format!("{}/models/{}:{}", BASE_URL, model(options), response_type);
+    let response = reqwest::Client::new()
+        .post(url)
+        .query(&[("key", api_key()?)])
+        .timeout(timeout)
+        .json(&request)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        let message = body["error"]["message"].as_str().unwrap_or("unknown error");
+        return Err(format!("Gemini API error ({}): {}", status, message).into());
+    }
+    Ok(response)
+}
+
+pub async fn search(query: &str, options: &SearchOptions) -> Result<Answer, Error> {
+    let response = post(
+        query,
+        options,
+        ResponseType::GenerateContent,
+        Duration::from_secs(30),
+    )
+    .await?;
+    let raw: serde_json::Value = response.json().await?;
+    to_answer(model(options), serde_json::from_value(raw.clone())?, raw)
+}
+
+/// Asks Gemini's `streamGenerateContent`, sending each chunk's text to `tokens` and merging
+/// the chunks into one answer.
+pub async fn search_streamed(
+    query: &str,
+    options: &SearchOptions,
+    tokens: &TokenSender,
+) -> Result<Answer, Error> {
+    let mut stream = post(
+        query,
+        options,
+        ResponseType::StreamGenerateContent,
+        Duration::from_secs(60),
+    )
+    .await?
+    .json_array_stream::<serde_json::Value>(MAX_CHUNK_LEN);
+    let mut merged: Option<GeminiResponse> = None;
+    // The streamed body is a JSON array of responses, kept whole as the raw payload.
+    let mut chunks = vec![];
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        chunks.push(chunk.clone());
+        let chunk: GeminiResponse = serde_json::from_value(chunk)?;
+        let text = chunk
+            .candidates
+            .first()
+            .into_iter()
+            .flat_map(|candidate| &candidate.content.parts)
+            .filter_map(|part| part.text.clone());
+        for text in text {
+            tokens.send(text);
+        }
+        merged = Some(match merged {
+            Some(merged) => merge(merged, chunk),
+            None => chunk,
+        });
+    }
+    to_answer(
+        model(options),
+        merged.ok_or("Gemini returned an empty stream")?,
+        serde_json::Value::Array(chunks),
+    )
+}
+
+/// Appends a streamed chunk to the response assembled so far; the last chunk
+/// carries the finish reason, safety ratings and usage for the whole answer.
+fn merge(mut merged: GeminiResponse, chunk: GeminiResponse) -> GeminiResponse {
+    match (
+        merged.candidates.first_mut(),
+        chunk.candidates.into_iter().next(),
+    ) {
+        (Some(candidate), Some(next)) => {
+            candidate.content.parts.extend(next.content.parts);
+            candidate.finish_reason = next.finish_reason.or(candidate.finish_reason.take());
+            if !next.safety_ratings.is_empty() {
+                candidate.safety_ratings = next.safety_ratings;
+            }
+        }
+        (None, Some(next)) => merged.candidates.push(next),
+        (_, None) => {}
+    }
+    merged.prompt_feedback = chunk.prompt_feedback.or(merged.prompt_feedback);
+    merged.usage_metadata = chunk.usage_metadata.or(merged.usage_metadata);
+    merged
+}
+
+fn to_safety_ratings(ratings: &[SafetyRating]) -> Vec<answer::SafetyRating> {
+    let name = |value: serde_json::Result<serde_json::Value>| {
+        value
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default()
+    };
+    ratings
+        .iter()
+        .map(|rating| answer::SafetyRating {
+            category: name(serde_json::to_value(&rating.category)),
+            probability: name(serde_json::to_value(&rating.probability)),
+            blocked: rating.blocked,
+        })
+        .collect()
+}
+
+/// `raw` is the body `response` was parsed from, `model` the model that was asked.
+fn to_answer(
+    model: &str,
+    response: GeminiResponse,
+    raw: serde_json::Value,
+) -> Result<Answer, Error> {
+    let Some(candidate) = response.candidates.first() else {
+        let ratings = response
+            .prompt_feedback
+            .map(|feedback| to_safety_ratings(&feedback.safety_ratings))
+            .unwrap_or_default();
+        let blocked_by = ratings
+            .iter()
+            .filter(|rating| rating.blocked)
+            .map(|rating| rating.category.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(format!(
+            "Gemini returned no candidates, prompt blocked by: {}",
+            blocked_by
+        )
+        .into());
     };
-    let response = client.post(30, &txt_request).await?;
-    println!("{:#?}", response);
-    Ok(())
+    let text = candidate
+        .content
+        .parts
+        .iter()
+        .filter_map(|part| part.text.as_deref())
+        .collect::<Vec<_>>()
+        .join("");
+    let safety_ratings = to_safety_ratings(&candidate.safety_ratings);
+    let usage = response.usage_metadata.as_ref().map(|usage| Usage {
+        prompt_tokens: usage.prompt_token_count,
+        completion_tokens: usage.candidates_token_count,
+        total_tokens: usage.total_token_count,
+    });
+    Ok(Answer {
+        model: model.to_string(),
+        text,
+        finish_reason: candidate.finish_reason.clone(),
+        usage,
+        safety_ratings,
+        raw,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Sampling;
+
+    #[test]
+    fn test_to_answer() {
+        let raw = serde_json::json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [{ "text": "The capital of France " }, { "text": "is Paris." }]
+                },
+                "finishReason": "STOP",
+                "index": 0,
+                "safetyRatings": [
+                    { "category": "HARM_CATEGORY_HARASSMENT", "probability": "NEGLIGIBLE" },
+                    { "category": "HARM_CATEGORY_HATE_SPEECH", "probability": "LOW" }
+                ]
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 7,
+                "candidatesTokenCount": 8,
+                "totalTokenCount": 15
+            }
+        });
+        let response: GeminiResponse = serde_json::from_value(raw.clone()).unwrap();
+        let answer = to_answer(DEFAULT_MODEL, response, raw).unwrap();
+        // The payload is stored as Gemini sent it.
+        assert_eq!(answer.raw["usageMetadata"]["promptTokenCount"], 7);
+        assert_eq!(
+            answer.raw["candidates"][0]["safetyRatings"][1]["probability"],
+            "LOW"
+        );
+        assert_eq!(answer.text, "The capital of France is Paris.");
+        assert_eq!(answer.finish_reason.as_deref(), Some("STOP"));
+        assert_eq!(answer.usage.unwrap().total_tokens, 15);
+        assert_eq!(answer.safety_ratings.len(), 2);
+        assert_eq!(
+            answer.safety_ratings[1].category,
+            "HARM_CATEGORY_HATE_SPEECH"
+        );
+        assert_eq!(answer.safety_ratings[1].probability, "LOW");
+    }
+
+    #[test]
+    fn test_merge_streamed_chunks() {
+        let chunk = |text: &str, finish_reason: Option<&str>| -> GeminiResponse {
+            serde_json::from_value(serde_json::json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": [{ "text": text }] },
+                    "finishReason": finish_reason,
+                    "index": 0,
+                    "safetyRatings": []
+                }],
+                "usageMetadata": finish_reason.map(|_| serde_json::json!({
+                    "promptTokenCount": 7,
+                    "candidatesTokenCount": 8,
+                    "totalTokenCount": 15
+                }))
+            }))
+            .unwrap()
+        };
+        let merged = merge(
+            chunk("The capital of ", None),
+            chunk("France is Paris.", Some("STOP")),
+        );
+        let answer = to_answer(DEFAULT_MODEL, merged, serde_json::Value::Null).unwrap();
+        assert_eq!(answer.text, "The capital of France is Paris.");
+        assert_eq!(answer.finish_reason.as_deref(), Some("STOP"));
+        assert_eq!(answer.usage.unwrap().total_tokens, 15);
+    }
+
+    #[test]
+    fn test_blocked_prompt() {
+        let response: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [],
+            "promptFeedback": {
+                "safetyRatings": [
+                    { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH", "blocked": true }
+                ]
+            }
+        }))
+        .unwrap();
+        let e = to_answer(DEFAULT_MODEL, response, serde_json::Value::Null).unwrap_err();
+        assert!(e.to_string().ends_with("HARM_CATEGORY_DANGEROUS_CONTENT"));
+    }
+
+    #[test]
+    fn test_request_sampling() {
+        let options = SearchOptions {
+            model: Some("gemini-1.5-pro".to_string()),
+            sampling: Sampling {
+                temperature: Some(0.5),
+                top_k: Some(40),
+                max_tokens: Some(256),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let body = serde_json::to_value(request("Capital of France?", &options).unwrap()).unwrap();
+        let config = &body["generationConfig"];
+        assert_eq!(config["temperature"], 0.5);
+        assert_eq!(config["topK"], 40);
+        assert_eq!(config["maxOutputTokens"], 256);
+        assert_eq!(model(&options), "gemini-1.5-pro");
+        let body =
+            serde_json::to_value(request("Capital of France?", &SearchOptions::default()).unwrap())
+                .unwrap();
+        assert!(body.get("generationConfig").is_none());
+
+        let options = SearchOptions {
+            sampling: Sampling {
+                repetition_penalty: Some(1.1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(request("Capital of France?", &options).is_err());
+    }
 }
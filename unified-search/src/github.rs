@@ -0,0 +1,498 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use clap::{Args, ValueEnum};
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::provider::{Capabilities, Error, SearchOptions, SearchProvider};
+use crate::result::{excerpt, UnifiedResult};
+
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+const DEFAULT_PER_PAGE: usize = 10;
+/// Warn once this few searches are left in the rate limit window.
+const RATE_LIMIT_WARNING: i64 = 3;
+
+#[derive(Debug, Clone, Copy, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Repositories,
+    Code,
+    Issues,
+}
+
+impl Kind {
+    /// Issues and pull requests when the query filters on them, repositories otherwise.
+    fn infer(query: &str) -> Kind {
+        let is_issue = query.split_whitespace().any(|term| {
+            matches!(
+                term,
+                "is:issue" | "is:pr" | "is:pull-request" | "type:issue" | "type:pr"
+            )
+        });
+        if is_issue {
+            Kind::Issues
+        } else {
+            Kind::Repositories
+        }
+    }
+}
+
+/// Filters for the GitHub search, on top of the qualifiers written in the query itself.
+#[derive(Debug, Clone, Default, Serialize, Args)]
+pub struct GithubSearch {
+    #[clap(
+        long,
+        value_enum,
+        help = "What to search, issues when the query has is:issue or is:pr, repositories otherwise"
+    )]
+    pub kind: Option<Kind>,
+    #[clap(
+        long,
+        help = "Only results in this language, same as a language: qualifier"
+    )]
+    pub language: Option<String>,
+    #[clap(
+        long,
+        help = "Only results from this repository (owner/name), same as a repo: qualifier"
+    )]
+    pub repo: Option<String>,
+    #[clap(
+        long,
+        help = "Sort by stars, forks or updated for repositories, comments, created or updated for issues; best match if omitted"
+    )]
+    pub sort: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchParams {
+    kind: Kind,
+    q: String,
+    sort: Option<String>,
+    per_page: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    items: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Owner {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Repository {
+    full_name: String,
+    html_url: String,
+    description: Option<String>,
+    owner: Owner,
+    stargazers_count: u64,
+    language: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodeRepository {
+    full_name: String,
+    owner: Owner,
+}
+
+#[derive(Debug, Deserialize)]
+struct Code {
+    path: String,
+    html_url: String,
+    repository: CodeRepository,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    number: u64,
+    title: String,
+    html_url: String,
+    state: String,
+    body: Option<String>,
+    user: Owner,
+    comments: u64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    pull_request: Option<serde_json::Value>,
+}
+
+pub struct Github {
+    api_url: String,
+    token: Option<String>,
+    search: GithubSearch,
+    client: reqwest::Client,
+}
+
+impl Github {
+    pub fn new(api_url: impl Into<String>, token: Option<String>, search: GithubSearch) -> Self {
+        Github {
+            api_url: api_url.into(),
+            token,
+            search,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Authenticates with `GITHUB_TOKEN` when it is set, which raises the rate limit
+    /// and is required for code search.
+    pub fn from_env(search: GithubSearch) -> Self {
+        Self::new(DEFAULT_API_URL, std::env::var("GITHUB_TOKEN").ok(), search)
+    }
+
+    fn params(&self, query: &str, options: &SearchOptions) -> SearchParams {
+        let mut q = query.to_string();
+        if let Some(language) = &self.search.language {
+            q += &format!(" language:{}", language);
+        }
+        if let Some(repo) = &self.search.repo {
+            q += &format!(" repo:{}", repo);
+        }
+        SearchParams {
+            kind: self.search.kind.unwrap_or_else(|| Kind::infer(query)),
+            q,
+            sort: self.search.sort.clone(),
+            per_page: options.limit.unwrap_or(DEFAULT_PER_PAGE).clamp(1, 100),
+        }
+    }
+
+    /// The raw items of the first page of results.
+    async fn get(&self, params: &SearchParams) -> Result<Vec<serde_json::Value>, Error> {
+        let kind = serde_json::to_value(params.kind)?;
+        let url = format!(
+            "{}/search/{}",
+            self.api_url,
+            kind.as_str().unwrap_or_default()
+        );
+        let mut request = self
+            .client
+            .get(url)
+            .header("accept", "application/vnd.github+json")
+            .header("x-github-api-version", "2022-11-28")
+            .header("user-agent", "unified-search")
+            .query(&[("q", &params.q), ("per_page", &params.per_page.to_string())]);
+        if let Some(sort) = &params.sort {
+            request = request.query(&[("sort", sort)]);
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("could not reach GitHub: {}", e))?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.api_error(status, &headers, &body));
+        }
+        if let Some(warning) = self.rate_limit_warning(&headers) {
+            eprintln!("{}", warning);
+        }
+        let response: SearchResponse = response.json().await?;
+        Ok(response.items)
+    }
+
+    /// Asks for a token when there is none, it raises the limit.
+    fn token_hint(&self) -> &'static str {
+        match self.token {
+            Some(_) => "",
+            None => " (set GITHUB_TOKEN for a higher limit)",
+        }
+    }
+
+    /// A warning when a successful search left only a few more in the rate limit window.
+    fn rate_limit_warning(&self, headers: &HeaderMap) -> Option<String> {
+        let remaining = header(headers, "x-ratelimit-remaining")?;
+        if remaining > RATE_LIMIT_WARNING {
+            return None;
+        }
+        let reset = rate_limit_reset(headers)
+            .map(|reset| format!(" until {}", reset.format("%H:%M:%S UTC")))
+            .unwrap_or_default();
+        Some(format!(
+            "warning: {} GitHub searches left{}{}",
+            remaining,
+            reset,
+            self.token_hint()
+        ))
+    }
+
+    fn api_error(&self, status: reqwest::StatusCode, headers: &HeaderMap, body: &str) -> Error {
+        let rate_limited = matches!(status.as_u16(), 403 | 429)
+            && (header(headers, "x-ratelimit-remaining") == Some(0)
+                || header(headers, "retry-after").is_some());
+        if rate_limited {
+            let reset = rate_limit_reset(headers)
+                .map(|reset| format!(", try again after {}", reset.format("%H:%M:%S UTC")))
+                .unwrap_or_default();
+            return format!("GitHub rate limit exceeded{}{}", reset, self.token_hint()).into();
+        }
+        let message = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|body| body.get("message")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| body.trim().to_string());
+        format!("GitHub API error ({}): {}", status.as_u16(), message).into()
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<i64> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// When the rate limit window resets, from `retry-after` or `x-ratelimit-reset`.
+fn rate_limit_reset(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    match header(headers, "retry-after") {
+        Some(seconds) => Some(Utc::now() + chrono::Duration::seconds(seconds)),
+        None => header(headers, "x-ratelimit-reset")
+            .and_then(|reset| Utc.timestamp_opt(reset, 0).single()),
+    }
+}
+
+fn repository_result(repository: Repository, raw: serde_json::Value, rank: usize) -> UnifiedResult {
+    let mut facts = vec![format!("★ {}", repository.stargazers_count)];
+    facts.extend(repository.language.clone());
+    facts.push(format!(
+        "updated {}",
+        repository.updated_at.format("%Y-%m-%d")
+    ));
+    let mut snippet = facts.join(" · ");
+    if let Some(description) = repository.description {
+        snippet = format!("{}\n{}", snippet, description);
+    }
+    UnifiedResult {
+        source: "github".to_string(),
+        title: repository.full_name,
+        url: Some(repository.html_url),
+        snippet,
+        author: Some(repository.owner.login),
+        published_at: Some(repository.created_at),
+        updated_at: Some(repository.updated_at),
+        language: repository.language,
+        rank,
+        score: Some(repository.stargazers_count as f64),
+        raw,
+        answer: None,
+    }
+}
+
+fn code_result(code: Code, raw: serde_json::Value, rank: usize) -> UnifiedResult {
+    UnifiedResult {
+        source: "github".to_string(),
+        title: format!("{}: {}", code.repository.full_name, code.path),
+        url: Some(code.html_url),
+        snippet: code.path,
+        author: Some(code.repository.owner.login),
+        published_at: None,
+        updated_at: None,
+        language: None,
+        rank,
+        score: None,
+        raw,
+        answer: None,
+    }
+}
+
+fn issue_result(issue: Issue, raw: serde_json::Value, rank: usize) -> UnifiedResult {
+    let kind = match issue.pull_request {
+        Some(_) => "pull request",
+        None => "issue",
+    };
+    let mut snippet = format!(
+        "{} {} · {} comments · updated {}",
+        issue.state,
+        kind,
+        issue.comments,
+        issue.updated_at.format("%Y-%m-%d")
+    );
+    if let Some(body) = issue.body.filter(|body| !body.trim().is_empty()) {
+        snippet = format!("{}\n{}", snippet, excerpt(&body, 200));
+    }
+    UnifiedResult {
+        source: "github".to_string(),
+        title: format!("#{} {}", issue.number, issue.title),
+        url: Some(issue.html_url),
+        snippet,
+        author: Some(issue.user.login),
+        published_at: Some(issue.created_at),
+        updated_at: Some(issue.updated_at),
+        language: None,
+        rank,
+        score: None,
+        raw,
+        answer: None,
+    }
+}
+
+fn ranked<T: DeserializeOwned>(
+    items: Vec<serde_json::Value>,
+    to_result: fn(T, serde_json::Value, usize) -> UnifiedResult,
+) -> Result<Vec<UnifiedResult>, Error> {
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, raw)| {
+            Ok(to_result(
+                serde_json::from_value(raw.clone())?,
+                raw,
+                index + 1,
+            ))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl SearchProvider for Github {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            links: true,
+            ..Default::default()
+        }
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(12 * 60 * 60)
+    }
+
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        serde_json::to_value(self.params(query, options)).unwrap_or_default()
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error> {
+        let params = self.params(query, options);
+        match params.kind {
+            Kind::Repositories => ranked(self.get(&params).await?, repository_result),
+            Kind::Code if self.token.is_none() => {
+                Err("GitHub code search requires GITHUB_TOKEN".into())
+            }
+            Kind::Code => ranked(self.get(&params).await?, code_result),
+            Kind::Issues => ranked(self.get(&params).await?, issue_result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_repositories() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/repositories"))
+            .and(query_param("q", "async runtime language:rust"))
+            .and(query_param("per_page", "5"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "total_count": 1,
+                "incomplete_results": false,
+                "items": [{
+                    "full_name": "tokio-rs/tokio",
+                    "html_url": "https://github.com/tokio-rs/tokio",
+                    "description": "A runtime for writing reliable asynchronous applications with Rust.",
+                    "owner": { "login": "tokio-rs" },
+                    "stargazers_count": 25000,
+                    "language": "Rust",
+                    "created_at": "2016-08-25T05:07:43Z",
+                    "updated_at": "2024-03-01T12:00:00Z",
+                    "score": 1.0
+                }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let github = Github::new(
+            server.uri(),
+            Some("secret".to_string()),
+            GithubSearch {
+                language: Some("rust".to_string()),
+                ..Default::default()
+            },
+        );
+        let options = SearchOptions {
+            limit: Some(5),
+            ..Default::default()
+        };
+        let results = github.search("async runtime", &options).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "tokio-rs/tokio");
+        assert_eq!(results[0].score, Some(25000.0));
+        assert!(results[0]
+            .snippet
+            .starts_with("★ 25000 · Rust · updated 2024-03-01"));
+        assert_eq!(results[0].language.as_deref(), Some("Rust"));
+        assert_eq!(
+            results[0].updated_at.unwrap().to_rfc3339(),
+            "2024-03-01T12:00:00+00:00"
+        );
+        assert_eq!(
+            results[0].published_at.unwrap().to_rfc3339(),
+            "2016-08-25T05:07:43+00:00"
+        );
+        assert_eq!(results[0].raw["score"], 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_issues_and_rate_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/issues"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("x-ratelimit-reset", "1709294400")
+                    .set_body_json(serde_json::json!({ "message": "API rate limit exceeded" })),
+            )
+            .mount(&server)
+            .await;
+
+        let github = Github::new(server.uri(), None, GithubSearch::default());
+        let e = github
+            .search(
+                "panic is:issue repo:tokio-rs/tokio",
+                &SearchOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "GitHub rate limit exceeded, try again after 12:00:00 UTC (set GITHUB_TOKEN for a higher limit)"
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_warning() {
+        let github = Github::new(DEFAULT_API_URL, None, GithubSearch::default());
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", "9".parse().unwrap());
+        headers.insert("x-ratelimit-reset", "1709294400".parse().unwrap());
+        assert_eq!(github.rate_limit_warning(&headers), None);
+        headers.insert("x-ratelimit-remaining", "2".parse().unwrap());
+        assert_eq!(
+            github.rate_limit_warning(&headers).as_deref(),
+            Some("warning: 2 GitHub searches left until 12:00:00 UTC (set GITHUB_TOKEN for a higher limit)")
+        );
+    }
+}
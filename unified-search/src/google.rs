@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use search_google::{GoogleSearchClient, SearchRequest};
+
+use crate::provider::{Capabilities, Error, SearchOptions, SearchProvider};
+use crate::result::UnifiedResult;
+
+pub struct Google {
+    request: SearchRequest,
+    client: Option<GoogleSearchClient>,
+}
+
+impl Google {
+    /// Searches with the filters of `request`, its query is replaced by the one searched for.
+    ///
+    /// The client is configured from the environment, `GOOGLE_SEARCH_API_KEY` and, unless
+    /// `request.cx` names the engine, `GOOGLE_SEARCH_ENGINE_ID`.
+    pub fn new(request: SearchRequest) -> Self {
+        Google {
+            client: GoogleSearchClient::from_env_for(request.cx.as_deref()).ok(),
+            request,
+        }
+    }
+
+    pub fn with_client(client: GoogleSearchClient, request: SearchRequest) -> Self {
+        Google {
+            request,
+            client: Some(client),
+        }
+    }
+
+    fn request(&self, query: &str, options: &SearchOptions) -> SearchRequest {
+        let mut request = SearchRequest {
+            q: Some(query.to_string()),
+            ..self.request.clone()
+        };
+        if let Some(limit) = options.limit {
+            // The total to fetch; it is requested in pages of at most 10.
+            request.num = Some(limit.clamp(1, search_google::MAX_RESULTS).to_string());
+        }
+        request
+    }
+}
+
+impl Default for Google {
+    fn default() -> Self {
+        Google::new(SearchRequest::default())
+    }
+}
+
+#[async_trait]
+impl SearchProvider for Google {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            links: true,
+            ..Default::default()
+        }
+    }
+
+    fn is_configured(&self) -> bool {
+        self.client.is_some()
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(24 * 60 * 60)
+    }
+
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        serde_json::to_value(self.request(query, options)).unwrap_or_default()
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error> {
+        let request = self.request(query, options);
+        let client = self
+            .client
+            .as_ref()
+            .ok_or("GOOGLE_SEARCH_API_KEY and GOOGLE_SEARCH_ENGINE_ID or --cx must be set")?;
+        let limit = request
+            .num
+            .as_deref()
+            .and_then(|num| num.parse().ok())
+            .unwrap_or(search_google::PAGE_SIZE);
+        let items = client.search_all(request, limit).await?;
+        Ok(items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| UnifiedResult::from_search_item(item, index + 1))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_from_options() {
+        let google = Google::new(SearchRequest {
+            q: Some("ignored".to_string()),
+            date_restrict: Some("d7".parse().unwrap()),
+            ..Default::default()
+        });
+        let options = SearchOptions {
+            limit: Some(25),
+            ..Default::default()
+        };
+        let request = google.request("rust", &options);
+        assert_eq!(request.q.as_deref(), Some("rust"));
+        assert_eq!(request.num.as_deref(), Some("25"));
+        assert_eq!(request.date_restrict.unwrap().to_string(), "d7");
+    }
+
+    #[tokio::test]
+    async fn test_search_from_fixture() {
+        let mut google = Google {
+            request: SearchRequest {
+                c2coff: Some(search_google::C2coff::Disabled),
+                cr: Some("IN".parse().unwrap()),
+                date_restrict: Some("d1".parse().unwrap()),
+                filter: Some(search_google::Filter::On),
+                lr: Some("en".parse().unwrap()),
+                ..Default::default()
+            },
+            client: None,
+        };
+        let options = SearchOptions {
+            limit: Some(10),
+            ..Default::default()
+        };
+        // The first page `search_all` asks for, all ten results fit on it.
+        let request = SearchRequest {
+            start: Some("1".to_string()),
+            ..google.request("reliance industries", &options)
+        };
+        let (_server, client) = search_google::replay::replay_in(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../search-google/fixtures"),
+            "reliance_industries",
+            &request,
+        )
+        .await;
+        google.client = Some(client);
+        let results = google
+            .search("reliance industries", &options)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 10);
+        assert_eq!(results[0].title, "Reliance Industries Limited");
+        assert_eq!(results[9].rank, 10);
+    }
+}
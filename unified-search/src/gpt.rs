@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use async_openai::config::OpenAIConfig;
+use async_openai::error::OpenAIError;
+use async_openai::types::{
+    ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequest,
+    CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
+    CreateChatCompletionStreamResponse, Stop,
+};
+use async_openai::Client;
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use crate::answer::{Answer, Usage};
+use crate::provider::{Capabilities, Error, SearchOptions, SearchProvider, TokenSender};
+use crate::result::UnifiedResult;
+
+pub const DEFAULT_MODEL: &str = "gpt-3.5-turbo";
+
+pub struct Gpt;
+
+#[async_trait]
+impl SearchProvider for Gpt {
+    fn name(&self) -> &'static str {
+        "gpt"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            answer: true,
+            model_selection: true,
+            streaming: true,
+            ..Default::default()
+        }
+    }
+
+    fn is_configured(&self) -> bool {
+        std::env::var("OPENAI_API_KEY").is_ok()
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(7 * 24 * 60 * 60)
+    }
+
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        request(query, options)
+            .ok()
+            .and_then(|request| serde_json::to_value(request).ok())
+            .unwrap_or_default()
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error> {
+        let request = request(query, options)?;
+        let answer = match &options.tokens {
+            Some(tokens) => search_streamed(request, tokens).await?,
+            None => search(request).await?,
+        };
+        Ok(vec![answer.into_result(self.name(), query)])
+    }
+}
+
+fn client() -> Result<Client<OpenAIConfig>, Error> {
+    let api_key = std::env::var("OPENAI_API_KEY").map_err(|_| "OPENAI_API_KEY must be set")?;
+    Ok(Client::with_config(
+        OpenAIConfig::new().with_api_key(api_key),
+    ))
+}
+
+/// The chat completion for `query` with the model and sampling of `options`; OpenAI has no
+/// top-k or repetition penalty, so those are refused rather than dropped.
+fn request(query: &str, options: &SearchOptions) -> Result<CreateChatCompletionRequest, Error> {
+    let sampling = &options.sampling;
+    if sampling.top_k.is_some() || sampling.repetition_penalty.is_some() {
+        return Err("gpt does not support --top-k or --repetition-penalty".into());
+    }
+    let mut args = CreateChatCompletionRequestArgs::default();
+    args.model(options.model.as_deref().unwrap_or(DEFAULT_MODEL))
+        .messages([ChatCompletionRequestUserMessageArgs::default()
+            .content(query)
+            .build()?
+            .into()]);
+    if let Some(temperature) = sampling.temperature {
+        args.temperature(temperature);
+    }
+    if let Some(top_p) = sampling.top_p {
+        args.top_p(top_p);
+    }
+    if let Some(max_tokens) = sampling.max_tokens {
+        args.max_tokens(u16::try_from(max_tokens).map_err(|_| "--max-tokens is at most 65535")?);
+    }
+    if !sampling.stop.is_empty() {
+        args.stop(Stop::StringArray(sampling.stop.clone()));
+    }
+    Ok(args.build()?)
+}
+
+pub async fn search(request: CreateChatCompletionRequest) -> Result<Answer, Error> {
+    let response = client()?.chat().create(request).await.map_err(map_error)?;
+    Ok(to_answer(response))
+}
+
+/// Streams the completion of `request`, sending each delta to `tokens` and assembling the
+/// chunks into one response.
+pub async fn search_streamed(
+    request: CreateChatCompletionRequest,
+    tokens: &TokenSender,
+) -> Result<Answer, Error> {
+    let mut stream = client()?
+        .chat()
+        .create_stream(request)
+        .await
+        .map_err(map_error)?;
+    let mut chunks = vec![];
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(map_error)?;
+        let text = chunk
+            .choices
+            .first()
+            .and_then(|choice| choice.delta.content.clone());
+        if let Some(text) = text {
+            tokens.send(text);
+        }
+        chunks.push(chunk);
+    }
+    Ok(to_answer(assemble(chunks)?))
+}
+
+/// Rebuilds the response a non-streamed request would have returned from the streamed chunks.
+/// Streams don't report token usage, so the response has none.
+fn assemble(
+    chunks: Vec<CreateChatCompletionStreamResponse>,
+) -> Result<CreateChatCompletionResponse, Error> {
+    let last = chunks.last().ok_or("OpenAI returned an empty stream")?;
+    let choices = chunks.iter().flat_map(|chunk| chunk.choices.first());
+    let text = choices
+        .clone()
+        .filter_map(|choice| choice.delta.content.as_deref())
+        .collect::<String>();
+    let finish_reason = choices.rev().find_map(|choice| choice.finish_reason);
+    let response = serde_json::json!({
+        "id": last.id,
+        "object": "chat.completion",
+        "created": last.created,
+        "model": last.model,
+        "system_fingerprint": last.system_fingerprint,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": text },
+            "finish_reason": finish_reason,
+        }],
+    });
+    Ok(serde_json::from_value(response)?)
+}
+
+fn to_answer(response: CreateChatCompletionResponse) -> Answer {
+    let raw = serde_json::to_value(&response).unwrap_or_default();
+    let choice = response.choices.into_iter().next();
+    Answer {
+        model: response.model,
+        text: choice
+            .as_ref()
+            .and_then(|choice| choice.message.content.clone())
+            .unwrap_or_default(),
+        finish_reason: choice
+            .and_then(|choice| choice.finish_reason)
+            .and_then(|reason| serde_json::to_value(reason).ok())
+            .and_then(|reason| reason.as_str().map(str::to_string)),
+        usage: response.usage.map(|usage| Usage {
+            prompt_tokens: usage.prompt_tokens.into(),
+            completion_tokens: usage.completion_tokens.into(),
+            total_tokens: usage.total_tokens.into(),
+        }),
+        safety_ratings: vec![],
+        raw,
+    }
+}
+
+fn map_error(e: OpenAIError) -> Error {
+    match e {
+        OpenAIError::ApiError(api) => {
+            let code = api
+                .code
+                .as_ref()
+                .and_then(|code| code.as_str())
+                .or(api.r#type.as_deref())
+                .unwrap_or("unknown");
+            format!("OpenAI API error ({}): {}", code, api.message).into()
+        }
+        OpenAIError::Reqwest(e) => format!("could not reach OpenAI: {}", e).into(),
+        e => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Sampling;
+
+    #[test]
+    fn test_to_answer() {
+        let response: CreateChatCompletionResponse = serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "gpt-3.5-turbo-0125",
+            "system_fingerprint": null,
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Paris." },
+                "logprobs": null,
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 9, "completion_tokens": 2, "total_tokens": 11 }
+        }))
+        .unwrap();
+        let answer = to_answer(response);
+        assert_eq!(answer.text, "Paris.");
+        assert_eq!(answer.model, "gpt-3.5-turbo-0125");
+        assert_eq!(answer.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(answer.usage.unwrap().total_tokens, 11);
+        assert_eq!(answer.raw["id"], "chatcmpl-123");
+    }
+
+    #[test]
+    fn test_assemble_streamed_chunks() {
+        let chunk = |content: Option<&str>, finish_reason: Option<&str>| {
+            serde_json::from_value(serde_json::json!({
+                "id": "chatcmpl-123",
+                "object": "chat.completion.chunk",
+                "created": 1677652288,
+                "model": "gpt-3.5-turbo-0125",
+                "system_fingerprint": null,
+                "choices": [{
+                    "index": 0,
+                    "delta": { "content": content },
+                    "logprobs": null,
+                    "finish_reason": finish_reason
+                }]
+            }))
+            .unwrap()
+        };
+        let chunks = vec![
+            chunk(Some("Par"), None),
+            chunk(Some("is."), None),
+            chunk(None, Some("stop")),
+        ];
+        let answer = to_answer(assemble(chunks).unwrap());
+        assert_eq!(answer.text, "Paris.");
+        assert_eq!(answer.model, "gpt-3.5-turbo-0125");
+        assert_eq!(answer.finish_reason.as_deref(), Some("stop"));
+        assert!(answer.usage.is_none());
+    }
+
+    #[test]
+    fn test_request_sampling() {
+        let options = SearchOptions {
+            model: Some("gpt-4".to_string()),
+            sampling: Sampling {
+                temperature: Some(0.2),
+                max_tokens: Some(64),
+                stop: vec!["\n\n".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let body = serde_json::to_value(request("Capital of France?", &options).unwrap()).unwrap();
+        assert_eq!(body["model"], "gpt-4");
+        assert_eq!(body["temperature"], 0.2f32 as f64);
+        assert_eq!(body["max_tokens"], 64);
+        assert_eq!(body["stop"], serde_json::json!(["\n\n"]));
+
+        let options = SearchOptions {
+            sampling: Sampling {
+                top_k: Some(40),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(request("Capital of France?", &options).is_err());
+    }
+}
@@ -0,0 +1,15 @@
+pub mod answer;
+pub mod cache;
+pub mod fanout;
+pub mod gemini;
+pub mod github;
+pub mod google;
+pub mod gpt;
+pub mod provider;
+pub mod reddit;
+pub mod result;
+pub mod similar;
+pub mod storage;
+pub mod together;
+pub mod wikipedia;
+pub mod youtube;
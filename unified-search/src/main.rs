@@ -1,41 +1,482 @@
-mod gemini;
-mod gpt;
+use std::io::{IsTerminal, Write};
+use std::sync::Arc;
+use std::time::Duration;
 
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand};
+use search_google::SearchRequest;
+use search_youtube::YoutubeSearchRequest;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use unified_search::answer::Answer;
+use unified_search::cache::{Cache, CacheMode};
+use unified_search::fanout::{self, SourceOutcome};
+use unified_search::github::{Github, GithubSearch};
+use unified_search::google::Google;
+use unified_search::provider::{self, Sampling, SearchOptions, SearchProvider, TokenSender};
+use unified_search::reddit::{self, Reddit, RedditSearch};
+use unified_search::result::UnifiedResult;
+use unified_search::similar;
+use unified_search::storage::Storage;
+use unified_search::wikipedia::{self, Wikipedia};
+use unified_search::youtube::Youtube;
 
 #[derive(Debug, Parser)]
+#[clap(
+    name = "us",
+    about = "Search the web, video sites and LLMs from one place"
+)]
 pub struct Input {
-    #[clap(short, long, help = "The source to search from")]
-    pub source: Option<Source>,
-    #[clap(short, long, help = "The model to use")]
-    pub model: Option<String>,
-    #[clap(short, long, help = "The query to search for")]
+    #[clap(subcommand)]
+    pub command: Command,
+    #[clap(
+        short,
+        long,
+        global = true,
+        help = "Per-source timeout in seconds, overrides each source's default"
+    )]
+    pub timeout: Option<u64>,
+    #[clap(
+        short = 'n',
+        long,
+        global = true,
+        help = "The maximum number of results per source"
+    )]
+    pub limit: Option<usize>,
+    #[clap(
+        long,
+        global = true,
+        help = "Ignore cached results and query every source again"
+    )]
+    pub refresh: bool,
+    #[clap(
+        long,
+        global = true,
+        conflicts_with = "refresh",
+        help = "Only answer from cached results, even stale ones"
+    )]
+    pub offline: bool,
+    #[clap(
+        long,
+        global = true,
+        help = "Show stale cached results immediately and refresh them in the background"
+    )]
+    pub stale_while_revalidate: bool,
+    #[clap(
+        long,
+        global = true,
+        help = "Don't offer the results of similar earlier queries"
+    )]
+    pub no_similar: bool,
+    #[clap(
+        long,
+        global = true,
+        help = "Print LLM answers once complete instead of as they are generated"
+    )]
+    pub no_stream: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[clap(about = "Search every configured source")]
+    All(LlmArgs),
+    #[clap(about = "Ask Google Gemini")]
+    Gemini(LlmArgs),
+    #[clap(about = "Chat with OpenAI GPT models", alias = "gpt3")]
+    Gpt(LlmArgs),
+    #[clap(about = "Complete with Llama and other models hosted by Together")]
+    Llama(LlmArgs),
+    #[clap(
+        about = "Search Google with the Custom Search JSON API",
+        long_about = None,
+        mut_arg("q", |arg| arg.required(true))
+    )]
+    Google(Box<SearchRequest>),
+    // search-youtube's own short flags clash with each other and with ours, so `us youtube`
+    // goes without them and takes the query positionally.
+    #[clap(
+        about = "Search YouTube videos, channels and playlists",
+        mut_args(|arg| arg.short(None)),
+        mut_arg("q", |arg| arg.long(None).value_name("QUERY").required(true))
+    )]
+    Youtube(Box<YoutubeSearchRequest>),
+    #[clap(about = "Search GitHub repositories, code, issues and pull requests")]
+    Github(GithubArgs),
+    #[clap(about = "Search Reddit posts")]
+    Reddit(RedditArgs),
+    #[clap(about = "Search Wikipedia")]
+    Wikipedia(WikipediaArgs),
+}
+
+impl Command {
+    /// The name of the source the command searches, as registered in the registry.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::All(_) => "all",
+            Command::Gemini(_) => "gemini",
+            Command::Gpt(_) => "gpt",
+            Command::Llama(_) => "llama",
+            Command::Google(_) => "google",
+            Command::Youtube(_) => "youtube",
+            Command::Github(_) => "github",
+            Command::Reddit(_) => "reddit",
+            Command::Wikipedia(_) => "wikipedia",
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct WikipediaArgs {
+    #[clap(help = "The query to search for")]
+    pub query: String,
+    #[clap(
+        long,
+        default_value = wikipedia::DEFAULT_LANGUAGE,
+        value_parser = wikipedia::parse_language,
+        help = "The language edition to search, e.g. de for de.wikipedia.org"
+    )]
+    pub lang: String,
+}
+
+#[derive(Debug, Args)]
+pub struct GithubArgs {
+    #[clap(help = "The query to search for, qualifiers like language:rust or is:issue included")]
+    pub query: String,
+    #[clap(flatten)]
+    pub search: GithubSearch,
+}
+
+#[derive(Debug, Args)]
+pub struct RedditArgs {
+    #[clap(help = "The query to search for")]
     pub query: String,
+    #[clap(flatten)]
+    pub search: RedditSearch,
 }
 
-#[derive(Debug, Clone, Parser, ValueEnum)]
-pub enum Source {
-    Gemini,
-    Gpt,
-    Google,
-    Youtube,
-    Github,
-    Reddit,
-    Wikipedia
+#[derive(Debug, Args)]
+pub struct LlmArgs {
+    #[clap(help = "The query to search for, or the prompt for LLM sources")]
+    pub query: String,
+    #[clap(short, long, help = "The model to use for LLM sources, e.g. gpt-4")]
+    pub model: Option<String>,
+    #[clap(long, help = "Sampling temperature for LLM sources that support it")]
+    pub temperature: Option<f32>,
+    #[clap(long, help = "Nucleus sampling probability mass, e.g. 0.7")]
+    pub top_p: Option<f32>,
+    #[clap(long, help = "Sample only from the k most likely tokens")]
+    pub top_k: Option<u32>,
+    #[clap(long, help = "The maximum number of tokens to generate")]
+    pub max_tokens: Option<u32>,
+    #[clap(long, help = "Penalty for repeating tokens, 1 means none")]
+    pub repetition_penalty: Option<f32>,
+    #[clap(long, help = "Stop generating at this sequence, can be repeated")]
+    pub stop: Vec<String>,
+}
+
+impl LlmArgs {
+    /// Moves the model and sampling flags into `options` and returns the query.
+    fn apply(self, options: &mut SearchOptions) -> String {
+        options.model = self.model;
+        options.sampling = Sampling {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            top_k: self.top_k,
+            max_tokens: self.max_tokens,
+            repetition_penalty: self.repetition_penalty,
+            stop: self.stop,
+        };
+        self.query
+    }
+}
+
+/// `streamed` results had their answer text printed while it was generated.
+fn print_results(results: &[UnifiedResult], streamed: bool) {
+    for result in results {
+        if let Some(answer) = Answer::from_result(result) {
+            if streamed {
+                println!("\n\n{}", answer.details());
+            } else {
+                println!("{}", answer);
+            }
+            continue;
+        }
+        println!("{}. {}", result.rank, result.title);
+        if let Some(url) = &result.url {
+            println!("Link: {}", url);
+        }
+        if let Some(author) = &result.author {
+            println!("By: {}", author);
+        }
+        if let Some(published_at) = &result.published_at {
+            println!("Published: {}", published_at.format("%Y-%m-%d"));
+        }
+        if let Some(updated_at) = &result.updated_at {
+            println!("Updated: {}", updated_at.format("%Y-%m-%d"));
+        }
+        if let Some(language) = &result.language {
+            println!("Language: {}", language);
+        }
+        println!("{}", result.snippet);
+    }
+}
+
+/// Shows the closest earlier query and its results, and asks whether to search anyway.
+async fn offer_similar(storage: &Storage, query: &str) -> Result<bool, provider::Error> {
+    let similar = similar::similar_queries(storage, query, similar::DEFAULT_THRESHOLD).await?;
+    let Some(earlier) = similar.first() else {
+        return Ok(true);
+    };
+    println!(
+        "You searched for a similar query on {}: \"{}\" (similarity {:.2})",
+        earlier.created_at.format("%Y-%m-%d %H:%M"),
+        earlier.text,
+        earlier.similarity
+    );
+    for (source, results) in similar::query_results(storage, earlier.query_id).await? {
+        println!("== {} ({} results, earlier) ==", source, results.len());
+        print_results(&results, false);
+    }
+    print!("Search the network anyway? [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Prints tokens as they arrive until every sender is dropped, returns whether any arrived.
+async fn print_tokens(mut tokens: UnboundedReceiver<String>) -> bool {
+    let mut streamed = false;
+    while let Some(token) = tokens.recv().await {
+        print!("{}", token);
+        std::io::stdout().flush().ok();
+        streamed = true;
+    }
+    streamed
+}
+
+async fn record(
+    storage: &Storage,
+    query: &str,
+    options: &SearchOptions,
+    outcomes: &[SourceOutcome],
+) -> Result<i64, provider::Error> {
+    let query_id = storage.record_query(query, options).await?;
+    record_runs(storage, query_id, outcomes).await?;
+    Ok(query_id)
+}
+
+async fn record_runs(
+    storage: &Storage,
+    query_id: i64,
+    outcomes: &[SourceOutcome],
+) -> Result<(), provider::Error> {
+    for outcome in outcomes {
+        storage.record_run(query_id, outcome).await?;
+    }
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() {
     let input = Input::parse();
-    match input.source {
-        Some(Source::Gemini) => {
-            gemini::search(&input.query).await.unwrap();
+    let registry = provider::default_registry();
+    let mut options = SearchOptions {
+        limit: input.limit,
+        timeout: input.timeout.map(Duration::from_secs),
+        ..Default::default()
+    };
+    let source = input.command.name();
+    let (query, provider): (String, Option<Arc<dyn SearchProvider>>) = match input.command {
+        Command::All(args) => (args.apply(&mut options), None),
+        Command::Gemini(args) | Command::Gpt(args) | Command::Llama(args) => {
+            (args.apply(&mut options), registry.get(source))
+        }
+        Command::Google(request) => (
+            request.q.clone().unwrap_or_default(),
+            Some(Arc::new(Google::new(*request))),
+        ),
+        Command::Youtube(request) => (
+            request.q.clone().unwrap_or_default(),
+            Some(Arc::new(Youtube::new(*request))),
+        ),
+        Command::Github(args) => (args.query, Some(Arc::new(Github::from_env(args.search)))),
+        Command::Reddit(args) => (
+            args.query,
+            Some(Arc::new(Reddit::new(reddit::DEFAULT_BASE_URL, args.search))),
+        ),
+        Command::Wikipedia(args) => (args.query, Some(Arc::new(Wikipedia::language(&args.lang)))),
+    };
+    let providers = match provider {
+        None if source == "all" => registry.configured(),
+        Some(provider) if provider.is_configured() => vec![provider],
+        Some(provider) => {
+            println!(
+                "Source {} is not configured, set its API key first",
+                provider.name()
+            );
+            return;
+        }
+        None => {
+            println!("Source {} is not supported yet", source);
+            return;
+        }
+    };
+    if providers.is_empty() {
+        println!("No source is configured, set the API keys listed in the README");
+        return;
+    }
+
+    let mode = match (input.refresh, input.offline) {
+        (true, _) => CacheMode::Refresh,
+        (_, true) => CacheMode::Offline,
+        _ => CacheMode::Default,
+    };
+    let storage = match Storage::open_default().await {
+        Ok(storage) => Some(storage),
+        Err(e) if mode == CacheMode::Offline => {
+            println!("Cannot run offline without the database: {}", e);
+            return;
         }
-        Some(Source::Gpt) => {
-            // gpt::search(input.query, input.model.unwrap()).await;
+        Err(e) => {
+            eprintln!(
+                "Could not open the database, searching without cache: {}",
+                e
+            );
+            None
         }
-        _ => {
-            println!("No source specified");
+    };
+    if let Some(storage) = &storage {
+        let interactive = std::io::stdin().is_terminal();
+        if mode == CacheMode::Default && !input.no_similar && interactive {
+            match offer_similar(storage, &query).await {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(e) => eprintln!("Could not look up similar queries: {}", e),
+            }
+        }
+    }
+    // Several answers streaming at once would interleave, so only a single source streams.
+    let printer = match providers.as_slice() {
+        [provider] if provider.capabilities().streaming && !input.no_stream => {
+            let (tokens, receiver) = TokenSender::channel();
+            options.tokens = Some(tokens);
+            Some(tokio::spawn(print_tokens(receiver)))
         }
+        _ => None,
+    };
+    let search = async {
+        match &storage {
+            Some(storage) => {
+                let cache = Cache::new(storage.clone(), mode, input.stale_while_revalidate);
+                let search = cache.search(&providers, &query, &options).await;
+                (search.outcomes, search.revalidation)
+            }
+            None => (fanout::search_all(&providers, &query, &options).await, None),
+        }
+    };
+    let (outcomes, revalidation) = tokio::select! {
+        searched = search => searched,
+        _ = tokio::signal::ctrl_c() => {
+            // Nothing is recorded for a cancelled search, a partial answer isn't worth caching.
+            eprintln!("\nCancelled");
+            return;
+        }
+    };
+    options.tokens = None;
+    let streamed = match printer {
+        Some(printer) => printer.await.unwrap_or_default(),
+        None => false,
+    };
+    let query_id = match &storage {
+        Some(storage) => match record(storage, &query, &options, &outcomes).await {
+            Ok(query_id) => Some(query_id),
+            Err(e) => {
+                eprintln!("Could not save the search history: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    print_outcomes(outcomes, providers.len() > 1, streamed);
+    if let Some(revalidation) = revalidation {
+        eprintln!("Refreshing stale results in the background...");
+        let outcomes = revalidation.await.unwrap_or_default();
+        if let (Some(storage), Some(query_id)) = (&storage, query_id) {
+            if let Err(e) = record_runs(storage, query_id, &outcomes).await {
+                eprintln!("Could not save the refreshed results: {}", e);
+            }
+        }
+    }
+}
+
+fn print_outcomes(outcomes: Vec<SourceOutcome>, with_headers: bool, streamed: bool) {
+    let mut errors = vec![];
+    for outcome in outcomes {
+        match outcome.results {
+            Ok(results) => {
+                if with_headers || outcome.cached_run.is_some() {
+                    let cached = match outcome.cached_run {
+                        Some(_) => ", cached",
+                        None => "",
+                    };
+                    println!(
+                        "== {} ({} results{}) ==",
+                        outcome.source,
+                        results.len(),
+                        cached
+                    );
+                }
+                print_results(&results, streamed);
+            }
+            Err(e) => errors.push((outcome.source, e)),
+        }
+    }
+    if !errors.is_empty() {
+        eprintln!("Errors:");
+        for (source, e) in errors {
+            eprintln!("  {}: {}", source, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        Input::command().debug_assert();
+        let input =
+            Input::parse_from(["us", "gpt3", "capital of France", "-m", "gpt-4", "-t", "5"]);
+        assert!(
+            matches!(input.command, Command::Gpt(ref args) if args.model.as_deref() == Some("gpt-4"))
+        );
+        assert_eq!(input.timeout, Some(5));
+        let input = Input::parse_from(["us", "google", "rust", "--num", "5", "--refresh"]);
+        let Command::Google(request) = input.command else {
+            panic!("expected the google command");
+        };
+        assert_eq!(request.q.as_deref(), Some("rust"));
+        assert_eq!(request.num.as_deref(), Some("5"));
+        assert!(input.refresh);
+        let input = Input::parse_from(["us", "google", "rust", "-d", "d7", "-n", "5", "-t", "3"]);
+        let Command::Google(request) = input.command else {
+            panic!("expected the google command");
+        };
+        assert_eq!(request.date_restrict.unwrap().to_string(), "d7");
+        assert_eq!((input.limit, input.timeout), (Some(5), Some(3)));
+        assert!(Input::try_parse_from(["us", "youtube"]).is_err());
+        let input = Input::parse_from(["us", "youtube", "rust", "-n", "5", "--order", "date"]);
+        let Command::Youtube(request) = input.command else {
+            panic!("expected the youtube command");
+        };
+        assert_eq!(request.q.as_deref(), Some("rust"));
+        assert_eq!(input.limit, Some(5));
+        let input = Input::parse_from(["us", "wikipedia", "Mercury", "--lang", "de"]);
+        assert!(matches!(input.command, Command::Wikipedia(ref args) if args.lang == "de"));
+        assert!(Input::try_parse_from(["us", "wikipedia", "Mercury", "--lang", "de.x/"]).is_err());
     }
 }
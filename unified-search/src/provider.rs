@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::result::UnifiedResult;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Receives the text of a generated answer piece by piece, as it arrives.
+#[derive(Debug, Clone)]
+pub struct TokenSender(UnboundedSender<String>);
+
+impl TokenSender {
+    pub fn channel() -> (TokenSender, UnboundedReceiver<String>) {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        (TokenSender(sender), receiver)
+    }
+
+    /// Passes `text` on to the receiver, if it is still there.
+    ///
+    /// The receiver is gone once the user stops watching; streaming providers keep assembling
+    /// the answer regardless, so it can still be returned and cached.
+    pub fn send(&self, text: String) {
+        self.0.send(text).ok();
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capabilities {
+    /// The provider returns a ranked list of links (search engines, video sites, ...).
+    pub links: bool,
+    /// The provider generates a free-form answer (LLMs).
+    pub answer: bool,
+    /// The provider honours `SearchOptions::model`.
+    pub model_selection: bool,
+    /// The provider sends its answer to `SearchOptions::tokens` while generating it.
+    pub streaming: bool,
+}
+
+/// Generation parameters for LLM sources that support them, unset ones keep the source's defaults.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Sampling {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub max_tokens: Option<u32>,
+    pub repetition_penalty: Option<f32>,
+    /// Sequences that end the generation when produced.
+    pub stop: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchOptions {
+    pub model: Option<String>,
+    pub limit: Option<usize>,
+    pub sampling: Sampling,
+    /// Overrides `SearchProvider::timeout` for every provider.
+    pub timeout: Option<Duration>,
+    /// Where streaming providers send tokens; they wait for the whole answer when unset.
+    #[serde(skip)]
+    pub tokens: Option<TokenSender>,
+}
+
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn capabilities(&self) -> Capabilities;
+
+    /// Whether the credentials this provider needs are available.
+    fn is_configured(&self) -> bool {
+        true
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(15)
+    }
+
+    /// How long a stored response is served from the cache before it is considered stale.
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(24 * 60 * 60)
+    }
+
+    /// The provider-specific request `query` and `options` translate to.
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        serde_json::json!({
+            "q": query,
+            "model": options.model,
+            "limit": options.limit,
+        })
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error>;
+}
+
+/// Identifies equivalent requests to one provider, ignoring case and spacing in the query.
+pub fn cache_key(provider: &dyn SearchProvider, query: &str, options: &SearchOptions) -> String {
+    let query = query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    // `serde_json::Value` keeps object keys sorted, so the JSON text is canonical.
+    format!(
+        "{}:{}",
+        provider.name(),
+        provider.request_params(&query, options)
+    )
+}
+
+#[derive(Default, Clone)]
+pub struct Registry {
+    providers: Vec<Arc<dyn SearchProvider>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, provider: impl SearchProvider + 'static) {
+        self.providers.push(Arc::new(provider));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn SearchProvider>> {
+        self.providers
+            .iter()
+            .find(|provider| provider.name() == name)
+            .cloned()
+    }
+
+    pub fn providers(&self) -> &[Arc<dyn SearchProvider>] {
+        &self.providers
+    }
+
+    pub fn configured(&self) -> Vec<Arc<dyn SearchProvider>> {
+        self.providers
+            .iter()
+            .filter(|provider| provider.is_configured())
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn default_registry() -> Registry {
+    let mut registry = Registry::new();
+    registry.register(crate::gemini::Gemini);
+    registry.register(crate::gpt::Gpt);
+    registry.register(crate::google::Google::default());
+    registry.register(crate::youtube::Youtube::default());
+    registry.register(crate::together::Together::from_env());
+    registry.register(crate::github::Github::from_env(Default::default()));
+    registry.register(crate::reddit::Reddit::new(
+        crate::reddit::DEFAULT_BASE_URL,
+        Default::default(),
+    ));
+    registry.register(crate::wikipedia::Wikipedia::language(
+        crate::wikipedia::DEFAULT_LANGUAGE,
+    ));
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    #[async_trait]
+    impl SearchProvider for Echo {
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                answer: true,
+                ..Default::default()
+            }
+        }
+
+        async fn search(
+            &self,
+            query: &str,
+            _options: &SearchOptions,
+        ) -> Result<Vec<UnifiedResult>, Error> {
+            Ok(vec![UnifiedResult::from_llm_response(
+                self.name(),
+                None,
+                query,
+                query.to_string(),
+                serde_json::Value::Null,
+            )])
+        }
+    }
+
+    #[test]
+    fn test_cache_key_is_canonical() {
+        let options = SearchOptions::default();
+        assert_eq!(
+            cache_key(&Echo, "  Rust   Lang ", &options),
+            cache_key(&Echo, "rust lang", &options)
+        );
+        let with_model = SearchOptions {
+            model: Some("gpt-4".to_string()),
+            ..Default::default()
+        };
+        assert_ne!(
+            cache_key(&Echo, "rust", &options),
+            cache_key(&Echo, "rust", &with_model)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registry_lookup() {
+        let mut registry = Registry::new();
+        registry.register(Echo);
+        assert!(registry.get("google").is_none());
+        let provider = registry.get("echo").unwrap();
+        let results = provider
+            .search("hello", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "hello");
+    }
+}
@@ -0,0 +1,448 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use clap::{Args, ValueEnum};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::provider::{Capabilities, Error, SearchOptions, SearchProvider};
+use crate::result::{excerpt, UnifiedResult};
+
+pub const DEFAULT_BASE_URL: &str = "https://www.reddit.com";
+/// Reddit throttles generic user agents hard, see <https://github.com/reddit-archive/reddit/wiki/API>.
+pub const DEFAULT_USER_AGENT: &str = "unified-search/0.1 (command line search tool)";
+const DEFAULT_LIMIT: usize = 10;
+/// How many posts have their comments fetched at once, more get throttled.
+const COMMENT_REQUESTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    Relevance,
+    New,
+    Top,
+    Comments,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Time {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+/// Filters for the Reddit search.
+#[derive(Debug, Clone, Default, Serialize, Args)]
+pub struct RedditSearch {
+    #[clap(long, help = "Only search this subreddit, e.g. rust")]
+    pub subreddit: Option<String>,
+    #[clap(long, value_enum, help = "How to sort posts, relevance if omitted")]
+    pub sort: Option<Sort>,
+    #[clap(
+        long,
+        value_enum,
+        help = "Only posts from the last hour, day, week, month or year; all time if omitted"
+    )]
+    pub time: Option<Time>,
+    #[clap(long, help = "Include the N top comments of every post")]
+    pub comments: Option<usize>,
+    #[clap(
+        long,
+        help = "The user agent to send, REDDIT_USER_AGENT or a generic one if omitted"
+    )]
+    #[serde(skip)]
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchParams {
+    q: String,
+    subreddit: Option<String>,
+    sort: Option<Sort>,
+    t: Option<Time>,
+    limit: usize,
+    comments: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Listing {
+    data: ListingData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListingData {
+    children: Vec<Thing>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Thing {
+    kind: String,
+    data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Post {
+    title: String,
+    permalink: String,
+    #[serde(default)]
+    selftext: String,
+    subreddit: String,
+    author: String,
+    score: i64,
+    num_comments: u64,
+    created_utc: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Comment {
+    author: String,
+    body: String,
+    score: i64,
+}
+
+pub struct Reddit {
+    base_url: String,
+    user_agent: String,
+    search: RedditSearch,
+    client: reqwest::Client,
+}
+
+impl Reddit {
+    pub fn new(base_url: impl Into<String>, search: RedditSearch) -> Self {
+        let user_agent = search
+            .user_agent
+            .clone()
+            .or_else(|| std::env::var("REDDIT_USER_AGENT").ok())
+            .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+        Reddit {
+            base_url: base_url.into(),
+            user_agent,
+            search,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn params(&self, query: &str, options: &SearchOptions) -> SearchParams {
+        SearchParams {
+            q: query.to_string(),
+            subreddit: self.search.subreddit.clone(),
+            sort: self.search.sort,
+            t: self.search.time,
+            limit: options.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 100),
+            comments: self.search.comments.unwrap_or_default(),
+        }
+    }
+
+    async fn get(&self, url: &str, query: &[(&str, String)]) -> Result<serde_json::Value, Error> {
+        let response = self
+            .client
+            .get(url)
+            .header("user-agent", &self.user_agent)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| format!("could not reach Reddit: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| body.get("message")?.as_str().map(str::to_string))
+                .unwrap_or_else(|| excerpt(&body, 200));
+            return Err(format!("Reddit API error ({}): {}", status.as_u16(), message).into());
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn posts(&self, params: &SearchParams) -> Result<Vec<serde_json::Value>, Error> {
+        let url = match &params.subreddit {
+            Some(subreddit) => {
+                check_subreddit(subreddit)?;
+                format!("{}/r/{}/search.json", self.base_url, subreddit)
+            }
+            None => format!("{}/search.json", self.base_url),
+        };
+        let mut query = vec![
+            ("q", params.q.clone()),
+            ("limit", params.limit.to_string()),
+            ("raw_json", "1".to_string()),
+        ];
+        if params.subreddit.is_some() {
+            query.push(("restrict_sr", "1".to_string()));
+        }
+        if let Some(sort) = params.sort {
+            query.push(("sort", to_param(sort)));
+        }
+        if let Some(t) = params.t {
+            query.push(("t", to_param(t)));
+        }
+        let listing: Listing = serde_json::from_value(self.get(&url, &query).await?)?;
+        Ok(things(listing, "t3"))
+    }
+
+    /// The `limit` highest voted top-level comments of the post at `permalink`.
+    async fn top_comments(&self, permalink: &str, limit: usize) -> Result<Vec<Comment>, Error> {
+        let url = format!("{}{}.json", self.base_url, permalink.trim_end_matches('/'));
+        let query = [
+            ("sort", "top".to_string()),
+            ("limit", limit.to_string()),
+            ("depth", "1".to_string()),
+            ("raw_json", "1".to_string()),
+        ];
+        // The post itself comes first, then its comments.
+        let (_, comments): (Listing, Listing) =
+            serde_json::from_value(self.get(&url, &query).await?)?;
+        things(comments, "t1")
+            .into_iter()
+            .take(limit)
+            .map(|comment| Ok(serde_json::from_value(comment)?))
+            .collect()
+    }
+}
+
+/// Subreddit names are 2 to 21 letters, digits or underscores; anything else would change the
+/// path of the request.
+fn check_subreddit(subreddit: &str) -> Result<(), Error> {
+    let valid = (2..=21).contains(&subreddit.len())
+        && subreddit
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!(
+            "{:?} is not a subreddit name, those are 2 to 21 letters, digits or underscores",
+            subreddit
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn to_param(value: impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// The data of the children of `kind`, skipping "load more" placeholders and the like.
+fn things(listing: Listing, kind: &str) -> Vec<serde_json::Value> {
+    listing
+        .data
+        .children
+        .into_iter()
+        .filter(|thing| thing.kind == kind)
+        .map(|thing| thing.data)
+        .collect()
+}
+
+fn post_result(
+    base_url: &str,
+    raw: serde_json::Value,
+    comments: Vec<Comment>,
+    rank: usize,
+) -> Result<UnifiedResult, Error> {
+    let post: Post = serde_json::from_value(raw.clone())?;
+    let mut snippet = format!(
+        "▲ {} · {} comments · r/{}",
+        post.score, post.num_comments, post.subreddit
+    );
+    if !post.selftext.trim().is_empty() {
+        snippet = format!("{}\n{}", snippet, excerpt(&post.selftext, 300));
+    }
+    for comment in &comments {
+        snippet = format!(
+            "{}\n> u/{} (▲ {}): {}",
+            snippet,
+            comment.author,
+            comment.score,
+            excerpt(&comment.body, 200)
+        );
+    }
+    Ok(UnifiedResult {
+        source: "reddit".to_string(),
+        title: post.title,
+        url: Some(format!("{}{}", base_url, post.permalink)),
+        snippet,
+        author: Some(format!("u/{}", post.author)),
+        published_at: Utc.timestamp_opt(post.created_utc as i64, 0).single(),
+        updated_at: None,
+        language: None,
+        rank,
+        score: Some(post.score as f64),
+        raw,
+        answer: None,
+    })
+}
+
+#[async_trait]
+impl SearchProvider for Reddit {
+    fn name(&self) -> &'static str {
+        "reddit"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            links: true,
+            ..Default::default()
+        }
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(6 * 60 * 60)
+    }
+
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        serde_json::to_value(self.params(query, options)).unwrap_or_default()
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error> {
+        let params = self.params(query, options);
+        let posts = self.posts(&params).await?;
+        let permalinks: Vec<Option<String>> = posts
+            .iter()
+            .map(|post| Some(post.get("permalink")?.as_str()?.to_string()))
+            .collect();
+        let limit = params.comments;
+        let comments: Vec<Vec<Comment>> = futures::stream::iter(permalinks)
+            .map(|permalink| async move {
+                match permalink {
+                    // A post whose comments can't be loaded is still worth showing.
+                    Some(permalink) if limit > 0 => self
+                        .top_comments(&permalink, limit)
+                        .await
+                        .unwrap_or_default(),
+                    _ => vec![],
+                }
+            })
+            .buffered(COMMENT_REQUESTS)
+            .collect()
+            .await;
+        posts
+            .into_iter()
+            .zip(comments)
+            .enumerate()
+            .map(|(index, (post, comments))| post_result(&self.base_url, post, comments, index + 1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn listing(kind: &str, children: Vec<serde_json::Value>) -> serde_json::Value {
+        serde_json::json!({
+            "kind": "Listing",
+            "data": {
+                "children": children
+                    .into_iter()
+                    .map(|data| serde_json::json!({ "kind": kind, "data": data }))
+                    .collect::<Vec<_>>()
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_search_subreddit_with_comments() {
+        let server = MockServer::start().await;
+        let permalink = "/r/rust/comments/abc123/async_traits_are_stable/";
+        Mock::given(method("GET"))
+            .and(path("/r/rust/search.json"))
+            .and(query_param("q", "async traits"))
+            .and(query_param("restrict_sr", "1"))
+            .and(query_param("sort", "top"))
+            .and(query_param("t", "week"))
+            .and(header("user-agent", "us-tests/1.0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(listing(
+                "t3",
+                vec![serde_json::json!({
+                    "title": "Async traits are stable",
+                    "permalink": permalink,
+                    "selftext": "",
+                    "subreddit": "rust",
+                    "author": "ferris",
+                    "score": 812,
+                    "num_comments": 97,
+                    "created_utc": 1703721600.0
+                })],
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/r/rust/comments/abc123/async_traits_are_stable.json"))
+            .and(query_param("sort", "top"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                listing("t3", vec![]),
+                listing(
+                    "t1",
+                    vec![serde_json::json!({ "author": "crab", "body": "Finally!", "score": 120 })]
+                ),
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let reddit = Reddit::new(
+            server.uri(),
+            RedditSearch {
+                subreddit: Some("rust".to_string()),
+                sort: Some(Sort::Top),
+                time: Some(Time::Week),
+                comments: Some(3),
+                user_agent: Some("us-tests/1.0".to_string()),
+            },
+        );
+        let results = reddit
+            .search("async traits", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let result = &results[0];
+        assert_eq!(result.title, "Async traits are stable");
+        assert_eq!(result.url, Some(format!("{}{}", server.uri(), permalink)));
+        assert_eq!(result.author.as_deref(), Some("u/ferris"));
+        assert_eq!(result.score, Some(812.0));
+        assert_eq!(
+            result.snippet,
+            "▲ 812 · 97 comments · r/rust\n> u/crab (▲ 120): Finally!"
+        );
+        assert!(result.raw.get("top_comments").is_none());
+        assert_eq!(
+            result.published_at.unwrap().to_rfc3339(),
+            "2023-12-28T00:00:00+00:00"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_subreddit() {
+        let server = MockServer::start().await;
+        for subreddit in ["rust/../..", "a?b", "r", "a_name_longer_than_21_chars"] {
+            let reddit = Reddit::new(
+                server.uri(),
+                RedditSearch {
+                    subreddit: Some(subreddit.to_string()),
+                    ..Default::default()
+                },
+            );
+            let e = reddit
+                .search("async", &SearchOptions::default())
+                .await
+                .unwrap_err();
+            assert!(e.to_string().contains("is not a subreddit name"), "{}", e);
+        }
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+}
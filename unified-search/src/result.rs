@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use search_google::SearchItem;
+use search_youtube::YoutubeItem;
+use serde::{Deserialize, Serialize};
+
+use crate::answer::AnswerDetails;
+
+/// A single search hit or LLM answer, normalized across every source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnifiedResult {
+    pub source: String,
+    pub title: String,
+    pub url: Option<String>,
+    /// Snippet for link results, the full answer text for LLM results.
+    pub snippet: String,
+    /// Author, channel or model that produced the result.
+    pub author: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    /// When the result last changed, for sources that track it.
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// The programming language, for code hosting sources.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// 1-based position of the result in the provider's response.
+    pub rank: usize,
+    pub score: Option<f64>,
+    /// The untouched provider payload for this result.
+    pub raw: serde_json::Value,
+    /// Set for LLM answers, see `Answer::into_result`.
+    #[serde(default)]
+    pub answer: Option<AnswerDetails>,
+}
+
+/// `text` on a single line, cut after `max_chars` characters.
+pub fn excerpt(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+impl UnifiedResult {
+    pub fn from_search_item(item: SearchItem, rank: usize) -> Self {
+        let raw = serde_json::to_value(&item).unwrap_or_default();
+        UnifiedResult {
+            source: "google".to_string(),
+            title: item.title,
+            url: Some(item.link),
+            snippet: item.snippet,
+            author: None,
+            published_at: None,
+            updated_at: None,
+            language: None,
+            rank,
+            score: None,
+            raw,
+            answer: None,
+        }
+    }
+
+    pub fn from_youtube_item(item: YoutubeItem, rank: usize) -> Self {
+        let raw = serde_json::to_value(&item).unwrap_or_default();
+        let url = match (item.id.video_id, item.id.channel_id) {
+            (Some(video_id), _) => Some(format!("https://www.youtube.com/watch?v={}", video_id)),
+            (None, Some(channel_id)) => {
+                Some(format!("https://www.youtube.com/channel/{}", channel_id))
+            }
+            (None, None) => None,
+        };
+        UnifiedResult {
+            source: "youtube".to_string(),
+            title: item.snippet.title,
+            url,
+            snippet: item.snippet.description,
+            author: Some(item.snippet.channel_title),
+            published_at: DateTime::parse_from_rfc3339(&item.snippet.published_at)
+                .ok()
+                .map(|date| date.with_timezone(&Utc)),
+            updated_at: None,
+            language: None,
+            rank,
+            score: None,
+            raw,
+            answer: None,
+        }
+    }
+
+    /// Wraps a generated answer; the query doubles as the title.
+    pub fn from_llm_response(
+        source: &str,
+        model: Option<&str>,
+        query: &str,
+        text: String,
+        raw: serde_json::Value,
+    ) -> Self {
+        UnifiedResult {
+            source: source.to_string(),
+            title: query.to_string(),
+            url: None,
+            snippet: text,
+            author: model.map(str::to_string),
+            published_at: Some(Utc::now()),
+            updated_at: None,
+            language: None,
+            rank: 1,
+            score: None,
+            raw,
+            answer: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_youtube_item() {
+        let item: YoutubeItem = serde_json::from_value(serde_json::json!({
+            "kind": "youtube#searchResult",
+            "etag": "etag",
+            "id": { "kind": "youtube#video", "videoId": "dQw4w9WgXcQ" },
+            "snippet": {
+                "publishedAt": "2009-10-25T06:57:33Z",
+                "title": "Never Gonna Give You Up",
+                "description": "The official video",
+                "channelTitle": "Rick Astley",
+                "publishTime": "2009-10-25T06:57:33Z"
+            }
+        }))
+        .unwrap();
+        let result = UnifiedResult::from_youtube_item(item, 3);
+        assert_eq!(result.source, "youtube");
+        assert_eq!(
+            result.url.as_deref(),
+            Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ")
+        );
+        assert_eq!(result.author.as_deref(), Some("Rick Astley"));
+        assert_eq!(result.rank, 3);
+        assert_eq!(
+            result.published_at.unwrap().to_rfc3339(),
+            "2009-10-25T06:57:33+00:00"
+        );
+        assert_eq!(result.raw["id"]["videoId"], "dQw4w9WgXcQ");
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+use crate::provider::Error;
+use crate::result::UnifiedResult;
+use crate::storage::Storage;
+
+/// Queries at or above this similarity are offered instead of a network search.
+pub const DEFAULT_THRESHOLD: f64 = 0.5;
+/// Matching through stored results counts for less than matching the query itself.
+const RESULT_WEIGHT: f64 = 0.8;
+
+#[derive(Debug, Clone)]
+pub struct SimilarQuery {
+    pub query_id: i64,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+    /// From 0 to 1, the token overlap (Jaccard index) between the two queries or the share
+    /// of the query's tokens found in one of the earlier results, weighted by `RESULT_WEIGHT`,
+    /// whichever is higher.
+    pub similarity: f64,
+}
+
+fn tokens(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (tokens(a), tokens(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// The share of `query`'s tokens that `text` contains.
+fn coverage(query: &BTreeSet<String>, text: &str) -> f64 {
+    if query.is_empty() {
+        return 0.0;
+    }
+    query.intersection(&tokens(text)).count() as f64 / query.len() as f64
+}
+
+/// An FTS5 expression matching any token of `text`, with every token quoted
+/// so user input can't inject FTS operators.
+fn match_expression(text: &str) -> Option<String> {
+    let tokens = tokens(text);
+    if tokens.is_empty() {
+        return None;
+    }
+    Some(
+        tokens
+            .iter()
+            .map(|token| format!("\"{}\"", token))
+            .collect::<Vec<_>>()
+            .join(" OR "),
+    )
+}
+
+/// Earlier queries with stored results whose text, or whose results' titles and
+/// snippets, share terms with `query`, most similar first.
+///
+/// Nothing is returned when `query` itself was asked before; the cache answers it.
+pub async fn similar_queries(
+    storage: &Storage,
+    query: &str,
+    threshold: f64,
+) -> Result<Vec<SimilarQuery>, Error> {
+    let Some(expression) = match_expression(query) else {
+        return Ok(vec![]);
+    };
+    let rows = sqlx::query(
+        "SELECT q.id, q.text, q.created_at FROM queries q
+         WHERE q.id IN (
+             SELECT rowid FROM queries_fts WHERE queries_fts MATCH ?1
+             UNION
+             SELECT runs.query_id FROM results_fts
+             JOIN results ON results.id = results_fts.rowid
+             JOIN runs ON runs.id = results.run_id
+             WHERE results_fts MATCH ?1
+         )
+         AND EXISTS (
+             SELECT 1 FROM runs WHERE runs.query_id = q.id AND runs.status IN ('ok', 'cached')
+         )
+         ORDER BY q.created_at DESC",
+    )
+    .bind(&expression)
+    .fetch_all(storage.pool())
+    .await?;
+    let matching_results = sqlx::query(
+        "SELECT runs.query_id, results.title, results.snippet FROM results_fts
+         JOIN results ON results.id = results_fts.rowid
+         JOIN runs ON runs.id = results.run_id
+         WHERE results_fts MATCH ?1",
+    )
+    .bind(&expression)
+    .fetch_all(storage.pool())
+    .await?;
+
+    let query_tokens = tokens(query);
+    // How well the best of each earlier query's results covers this one.
+    let mut result_scores: HashMap<i64, f64> = HashMap::new();
+    for row in matching_results {
+        let text = format!(
+            "{} {}",
+            row.get::<String, _>("title"),
+            row.get::<String, _>("snippet")
+        );
+        let score = RESULT_WEIGHT * coverage(&query_tokens, &text);
+        let best = result_scores.entry(row.get("query_id")).or_default();
+        *best = best.max(score);
+    }
+    if rows
+        .iter()
+        .any(|row| tokens(row.get("text")) == query_tokens)
+    {
+        return Ok(vec![]);
+    }
+    let mut seen = HashSet::new();
+    let mut similar = vec![];
+    for row in rows {
+        let text: String = row.get("text");
+        let text_tokens = tokens(&text);
+        // Rows are newest first, so only the latest of identical queries is kept.
+        if !seen.insert(text_tokens) {
+            continue;
+        }
+        let query_id: i64 = row.get("id");
+        let similarity =
+            similarity(query, &text).max(result_scores.get(&query_id).copied().unwrap_or_default());
+        if similarity < threshold {
+            continue;
+        }
+        similar.push(SimilarQuery {
+            query_id,
+            created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+            text,
+            similarity,
+        });
+    }
+    similar.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    Ok(similar)
+}
+
+/// The results each source returned for an earlier query, following cache hits
+/// back to the run that owns the results.
+pub async fn query_results(
+    storage: &Storage,
+    query_id: i64,
+) -> Result<Vec<(String, Vec<UnifiedResult>)>, Error> {
+    let runs = sqlx::query(
+        "SELECT source, COALESCE(cached_run_id, id) AS run_id FROM runs
+         WHERE query_id = ? AND status IN ('ok', 'cached')
+         ORDER BY id",
+    )
+    .bind(query_id)
+    .fetch_all(storage.pool())
+    .await?;
+    let mut sources = HashSet::new();
+    let mut results = vec![];
+    for run in runs {
+        let source: String = run.get("source");
+        if !sources.insert(source.clone()) {
+            continue;
+        }
+        let run_results = storage.results(run.get("run_id"), &source).await?;
+        results.push((source, run_results));
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::fanout::SourceOutcome;
+    use crate::provider::SearchOptions;
+
+    async fn record(storage: &Storage, query: &str, title: &str) {
+        let query_id = storage
+            .record_query(query, &SearchOptions::default())
+            .await
+            .unwrap();
+        let outcome = SourceOutcome {
+            source: "google".to_string(),
+            cache_key: format!("google:{}", query),
+            cached_run: None,
+            results: Ok(vec![UnifiedResult {
+                source: "google".to_string(),
+                title: title.to_string(),
+                url: Some("https://example.com".to_string()),
+                snippet: String::new(),
+                author: None,
+                published_at: None,
+                updated_at: None,
+                language: None,
+                rank: 1,
+                score: None,
+                raw: serde_json::Value::Null,
+                answer: None,
+            }]),
+            started_at: Utc::now(),
+            elapsed: Duration::ZERO,
+        };
+        storage.record_run(query_id, &outcome).await.unwrap();
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Rust async", "rust, ASYNC"), 1.0);
+        assert_eq!(similarity("rust async runtime", "rust async"), 2.0 / 3.0);
+        assert_eq!(similarity("rust", "python"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_similar_queries() {
+        let storage = Storage::in_memory().await.unwrap();
+        record(&storage, "rust async runtime", "Tokio").await;
+        record(&storage, "python asyncio", "asyncio docs").await;
+        record(&storage, "best web framework", "Axum: a rust web framework").await;
+
+        let similar = similar_queries(&storage, "Rust async", DEFAULT_THRESHOLD)
+            .await
+            .unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].text, "rust async runtime");
+        assert_eq!(similar[0].similarity, 2.0 / 3.0);
+
+        // Found through the result title alone.
+        let similar = similar_queries(&storage, "axum", DEFAULT_THRESHOLD)
+            .await
+            .unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].text, "best web framework");
+        assert_eq!(similar[0].similarity, RESULT_WEIGHT);
+
+        let results = query_results(&storage, similar[0].query_id).await.unwrap();
+        assert_eq!(results[0].0, "google");
+        assert_eq!(results[0].1.len(), 1);
+
+        assert!(similar_queries(&storage, "Rust Async runtime", 0.0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+
+use crate::fanout::SourceOutcome;
+use crate::provider::{Error, SearchOptions};
+use crate::result::UnifiedResult;
+
+/// A successful network run kept in the database.
+#[derive(Debug, Clone)]
+pub struct StoredRun {
+    pub id: i64,
+    pub started_at: DateTime<Utc>,
+    pub results: Vec<UnifiedResult>,
+}
+
+/// Every query, per-source run and result the CLI has produced.
+#[derive(Clone)]
+pub struct Storage {
+    pool: SqlitePool,
+}
+
+impl Storage {
+    /// Opens the database at `UNIFIED_SEARCH_DB_PATH`, or `~/.unified-search.db` when unset.
+    pub async fn open_default() -> Result<Self, Error> {
+        let path = match std::env::var("UNIFIED_SEARCH_DB_PATH") {
+            Ok(path) => path,
+            Err(_) => {
+                let home = std::env::var("HOME")
+                    .map_err(|_| "neither UNIFIED_SEARCH_DB_PATH nor HOME is set")?;
+                format!("{}/.unified-search.db", home)
+            }
+        };
+        Self::open(&path).await
+    }
+
+    pub async fn open(path: &str) -> Result<Self, Error> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        Self::migrate(pool).await
+    }
+
+    pub async fn in_memory() -> Result<Self, Error> {
+        // Every connection to `:memory:` is a separate database, so keep exactly one.
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")?;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+        Self::migrate(pool).await
+    }
+
+    async fn migrate(pool: SqlitePool) -> Result<Self, Error> {
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        Ok(Storage { pool })
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub async fn record_query(&self, query: &str, options: &SearchOptions) -> Result<i64, Error> {
+        let id = sqlx::query("INSERT INTO queries (text, parameters, created_at) VALUES (?, ?, ?)")
+            .bind(query)
+            .bind(serde_json::to_string(options)?)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?
+            .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Stores a run and all of its results in one transaction.
+    pub async fn record_run(&self, query_id: i64, outcome: &SourceOutcome) -> Result<i64, Error> {
+        let mut tx = self.pool.begin().await?;
+        let (status, error) = match (&outcome.results, outcome.cached_run) {
+            (Ok(_), Some(_)) => ("cached", None),
+            (Ok(_), None) => ("ok", None),
+            (Err(e), _) => ("error", Some(e.as_str())),
+        };
+        let run_id = sqlx::query(
+            "INSERT INTO runs
+                (query_id, source, status, error, started_at, elapsed_ms, cache_key, cached_run_id)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(query_id)
+        .bind(&outcome.source)
+        .bind(status)
+        .bind(error)
+        .bind(outcome.started_at.to_rfc3339())
+        .bind(outcome.elapsed.as_millis() as i64)
+        .bind(&outcome.cache_key)
+        .bind(outcome.cached_run)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        // Cached runs point at the run that owns the results instead of copying them.
+        let results = match outcome.cached_run {
+            Some(_) => None,
+            None => outcome.results.as_ref().ok(),
+        };
+        for result in results.into_iter().flatten() {
+            sqlx::query(
+                "INSERT INTO results
+                    (run_id, rank, title, url, snippet, author, published_at, updated_at, language,
+                     score, raw, answer)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(run_id)
+            .bind(result.rank as i64)
+            .bind(&result.title)
+            .bind(&result.url)
+            .bind(&result.snippet)
+            .bind(&result.author)
+            .bind(result.published_at.map(|date| date.to_rfc3339()))
+            .bind(result.updated_at.map(|date| date.to_rfc3339()))
+            .bind(&result.language)
+            .bind(result.score)
+            .bind(serde_json::to_string(&result.raw)?)
+            .bind(
+                result
+                    .answer
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(run_id)
+    }
+
+    /// The most recent successful network run for `cache_key`, with its results.
+    pub async fn latest_run(
+        &self,
+        source: &str,
+        cache_key: &str,
+    ) -> Result<Option<StoredRun>, Error> {
+        let Some(run) = sqlx::query(
+            "SELECT id, started_at FROM runs
+             WHERE source = ? AND cache_key = ? AND status = 'ok'
+             ORDER BY started_at DESC, id DESC
+             LIMIT 1",
+        )
+        .bind(source)
+        .bind(cache_key)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+        let id: i64 = run.get("id");
+        let started_at = DateTime::parse_from_rfc3339(run.get("started_at"))?.with_timezone(&Utc);
+        Ok(Some(StoredRun {
+            id,
+            started_at,
+            results: self.results(id, source).await?,
+        }))
+    }
+
+    pub async fn results(&self, run_id: i64, source: &str) -> Result<Vec<UnifiedResult>, Error> {
+        sqlx::query(
+            "SELECT rank, title, url, snippet, author, published_at, updated_at, language, score,
+                    raw, answer
+             FROM results
+             WHERE run_id = ?
+             ORDER BY rank",
+        )
+        .bind(run_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| result_from_row(row, source))
+        .collect()
+    }
+}
+
+fn result_from_row(row: &SqliteRow, source: &str) -> Result<UnifiedResult, Error> {
+    let date = |column: &str| -> Result<Option<DateTime<Utc>>, Error> {
+        Ok(row
+            .get::<Option<&str>, _>(column)
+            .map(DateTime::parse_from_rfc3339)
+            .transpose()?
+            .map(|date| date.with_timezone(&Utc)))
+    };
+    Ok(UnifiedResult {
+        source: source.to_string(),
+        title: row.get("title"),
+        url: row.get("url"),
+        snippet: row.get("snippet"),
+        author: row.get("author"),
+        published_at: date("published_at")?,
+        updated_at: date("updated_at")?,
+        language: row.get("language"),
+        rank: row.get::<i64, _>("rank") as usize,
+        score: row.get("score"),
+        raw: serde_json::from_str(row.get("raw"))?,
+        answer: row
+            .get::<Option<&str>, _>("answer")
+            .map(serde_json::from_str)
+            .transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::answer::Answer;
+
+    #[tokio::test]
+    async fn test_record_query_and_runs() {
+        let storage = Storage::in_memory().await.unwrap();
+        let options = SearchOptions {
+            model: Some("gemini-pro".to_string()),
+            ..Default::default()
+        };
+        let query_id = storage.record_query("rust", &options).await.unwrap();
+        let ok = SourceOutcome {
+            source: "gemini".to_string(),
+            cache_key: "gemini:rust".to_string(),
+            cached_run: None,
+            results: Ok(vec![Answer {
+                model: "gemini-pro".to_string(),
+                text: "A systems language".to_string(),
+                finish_reason: Some("STOP".to_string()),
+                usage: None,
+                safety_ratings: vec![],
+                raw: serde_json::json!({ "candidates": [] }),
+            }
+            .into_result("gemini", "rust")]),
+            started_at: Utc::now(),
+            elapsed: Duration::from_millis(120),
+        };
+        let failed = SourceOutcome {
+            source: "google".to_string(),
+            cache_key: "google:rust".to_string(),
+            cached_run: None,
+            results: Err("quota exceeded".to_string()),
+            started_at: Utc::now(),
+            elapsed: Duration::from_millis(5),
+        };
+        let ok_run = storage.record_run(query_id, &ok).await.unwrap();
+        storage.record_run(query_id, &failed).await.unwrap();
+
+        let parameters: String = sqlx::query("SELECT parameters FROM queries WHERE id = ?")
+            .bind(query_id)
+            .fetch_one(storage.pool())
+            .await
+            .unwrap()
+            .get(0);
+        assert!(parameters.contains("gemini-pro"));
+        let runs = sqlx::query("SELECT source, status, error FROM runs ORDER BY id")
+            .fetch_all(storage.pool())
+            .await
+            .unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[1].get::<String, _>("status"), "error");
+        assert_eq!(runs[1].get::<String, _>("error"), "quota exceeded");
+        let raw: String = sqlx::query("SELECT raw FROM results")
+            .fetch_one(storage.pool())
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(raw, r#"{"candidates":[]}"#);
+
+        let cached = storage.latest_run("gemini", "gemini:rust").await.unwrap();
+        let cached = cached.unwrap();
+        assert_eq!(cached.id, ok_run);
+        assert_eq!(cached.results[0].snippet, "A systems language");
+        assert_eq!(cached.results[0].author.as_deref(), Some("gemini-pro"));
+        let answer = Answer::from_result(&cached.results[0]).unwrap();
+        assert_eq!(answer.finish_reason.as_deref(), Some("STOP"));
+        assert_eq!(answer.raw, serde_json::json!({ "candidates": [] }));
+        assert!(storage
+            .latest_run("google", "google:rust")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
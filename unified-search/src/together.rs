@@ -0,0 +1,379 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::answer::{Answer, Usage};
+use crate::provider::{Capabilities, Error, SearchOptions, SearchProvider, TokenSender};
+use crate::result::UnifiedResult;
+
+pub const DEFAULT_ENDPOINT: &str = "https://api.together.xyz/inference";
+pub const DEFAULT_MODEL: &str = "togethercomputer/llama-2-70b-chat";
+const DEFAULT_MAX_TOKENS: u32 = 512;
+
+/// A request to the Together inference API, see <https://docs.together.ai/docs/inference-rest>.
+#[derive(Debug, Clone, Serialize)]
+pub struct InferenceRequest {
+    pub model: String,
+    pub prompt: String,
+    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repetition_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    pub stream_tokens: bool,
+}
+
+impl InferenceRequest {
+    pub fn new(query: &str, options: &SearchOptions) -> Self {
+        let sampling = &options.sampling;
+        InferenceRequest {
+            model: options
+                .model
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            prompt: query.to_string(),
+            max_tokens: sampling.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            top_k: sampling.top_k,
+            repetition_penalty: sampling.repetition_penalty,
+            stop: sampling.stop.clone(),
+            stream_tokens: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InferenceResponse {
+    model: Option<String>,
+    output: Output,
+}
+
+#[derive(Debug, Deserialize)]
+struct Output {
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    #[serde(default)]
+    text: String,
+    finish_reason: Option<String>,
+}
+
+/// One server-sent event of a `stream_tokens` response.
+#[derive(Debug, Deserialize)]
+struct StreamEvent {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+/// Llama and the other open models hosted by Together.
+pub struct Together {
+    endpoint: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl Together {
+    pub fn new(endpoint: impl Into<String>, api_key: Option<String>) -> Self {
+        Together {
+            endpoint: endpoint.into(),
+            api_key,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Reads `TOGETHER_API_KEY`, and `TOGETHER_ENDPOINT_URL` to override the default endpoint.
+    pub fn from_env() -> Self {
+        let endpoint =
+            std::env::var("TOGETHER_ENDPOINT_URL").unwrap_or_else(|_| DEFAULT_ENDPOINT.to_string());
+        Self::new(endpoint, std::env::var("TOGETHER_API_KEY").ok())
+    }
+
+    async fn post(&self, request: &InferenceRequest) -> Result<reqwest::Response, Error> {
+        let api_key = self
+            .api_key
+            .as_deref()
+            .ok_or("TOGETHER_API_KEY must be set")?;
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(api_key)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| format!("could not reach Together: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(api_error(status, &body));
+        }
+        Ok(response)
+    }
+
+    pub async fn complete(&self, request: &InferenceRequest) -> Result<Answer, Error> {
+        let raw: serde_json::Value = self.post(request).await?.json().await?;
+        let response: InferenceResponse = serde_json::from_value(raw.clone())?;
+        let choice = response.output.choices.into_iter().next();
+        Ok(Answer {
+            model: response.model.unwrap_or_else(|| request.model.clone()),
+            text: choice
+                .as_ref()
+                .map(|choice| choice.text.clone())
+                .unwrap_or_default(),
+            finish_reason: choice.and_then(|choice| choice.finish_reason),
+            usage: response.output.usage,
+            safety_ratings: vec![],
+            raw,
+        })
+    }
+
+    /// Completes with `stream_tokens`, reading Together's server-sent events until `[DONE]` and
+    /// sending the text of each to `tokens`.
+    pub async fn complete_streamed(
+        &self,
+        request: &InferenceRequest,
+        tokens: &TokenSender,
+    ) -> Result<Answer, Error> {
+        let request = InferenceRequest {
+            stream_tokens: true,
+            ..request.clone()
+        };
+        let mut body = self.post(&request).await?.bytes_stream();
+        let mut answer = Answer {
+            model: request.model.clone(),
+            text: String::new(),
+            finish_reason: None,
+            usage: None,
+            safety_ratings: vec![],
+            raw: serde_json::Value::Null,
+        };
+        // Events can be split across chunks, so only complete lines are parsed.
+        let mut buffer = vec![];
+        'events: while let Some(chunk) = body.next().await {
+            buffer.extend_from_slice(&chunk?);
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line = buffer.drain(..=end).collect::<Vec<_>>();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                    continue;
+                };
+                if data == "[DONE]" {
+                    break 'events;
+                }
+                let event: StreamEvent = serde_json::from_str(data)?;
+                if let Some(choice) = event.choices.into_iter().next() {
+                    tokens.send(choice.text.clone());
+                    answer.text.push_str(&choice.text);
+                    answer.finish_reason = choice.finish_reason.or(answer.finish_reason);
+                }
+                answer.usage = event.usage.or(answer.usage);
+            }
+        }
+        // Shaped like a non-streamed response, so both kinds of answers store the same payload.
+        answer.raw = serde_json::json!({
+            "model": answer.model,
+            "output": {
+                "choices": [{ "text": answer.text, "finish_reason": answer.finish_reason }],
+                "usage": answer.usage,
+            },
+        });
+        Ok(answer)
+    }
+}
+
+/// Together reports errors as `{"error": "..."}` or `{"error": {"message": "..."}}`.
+fn api_error(status: reqwest::StatusCode, body: &str) -> Error {
+    let error = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|body| {
+            let error = body.get("error")?;
+            error
+                .as_str()
+                .or_else(|| error.get("message")?.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.trim().to_string());
+    format!("Together API error ({}): {}", status.as_u16(), error).into()
+}
+
+#[async_trait]
+impl SearchProvider for Together {
+    fn name(&self) -> &'static str {
+        "llama"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            answer: true,
+            model_selection: true,
+            streaming: true,
+            ..Default::default()
+        }
+    }
+
+    fn is_configured(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(7 * 24 * 60 * 60)
+    }
+
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        serde_json::to_value(InferenceRequest::new(query, options)).unwrap_or_default()
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error> {
+        let request = InferenceRequest::new(query, options);
+        let answer = match &options.tokens {
+            Some(tokens) => self.complete_streamed(&request, tokens).await?,
+            None => self.complete(&request).await?,
+        };
+        Ok(vec![answer.into_result(self.name(), query)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::provider::Sampling;
+
+    fn options() -> SearchOptions {
+        SearchOptions {
+            model: Some("togethercomputer/llama-2-7b-chat".to_string()),
+            sampling: Sampling {
+                temperature: Some(0.7),
+                top_k: Some(50),
+                stop: vec!["\n\n".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn together(server: &MockServer) -> Together {
+        Together::new(
+            format!("{}/inference", server.uri()),
+            Some("secret".to_string()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_complete() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/inference"))
+            .and(header("authorization", "Bearer secret"))
+            .and(body_partial_json(serde_json::json!({
+                "model": "togethercomputer/llama-2-7b-chat",
+                "prompt": "The capital of France is",
+                "max_tokens": 512,
+                "temperature": 0.7,
+                "top_k": 50,
+                "stop": ["\n\n"],
+                "stream_tokens": false
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "8a1f",
+                "status": "finished",
+                "model": "togethercomputer/llama-2-7b-chat",
+                "output": {
+                    "choices": [{ "text": " Paris.", "finish_reason": "eos" }],
+                    "usage": { "prompt_tokens": 6, "completion_tokens": 3, "total_tokens": 9 }
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let results = together(&server)
+            .search("The capital of France is", &options())
+            .await
+            .unwrap();
+        let answer = Answer::from_result(&results[0]).unwrap();
+        assert_eq!(answer.text, " Paris.");
+        assert_eq!(answer.finish_reason.as_deref(), Some("eos"));
+        assert_eq!(answer.usage.unwrap().total_tokens, 9);
+        assert_eq!(answer.raw["id"], "8a1f");
+    }
+
+    #[tokio::test]
+    async fn test_complete_streamed() {
+        let server = MockServer::start().await;
+        let events = [
+            r#"data: {"choices":[{"text":" Par"}],"usage":null}"#,
+            r#"data: {"choices":[{"text":"is."}],"usage":null}"#,
+            r#"data: {"choices":[{"text":"","finish_reason":"eos"}],"usage":{"prompt_tokens":6,"completion_tokens":3,"total_tokens":9}}"#,
+            "data: [DONE]",
+        ]
+        .map(|event| format!("{}\n\n", event))
+        .concat();
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({ "stream_tokens": true }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(events, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let (tokens, mut receiver) = TokenSender::channel();
+        let options = SearchOptions {
+            tokens: Some(tokens),
+            ..options()
+        };
+        let results = together(&server)
+            .search("The capital of France is", &options)
+            .await
+            .unwrap();
+        drop(options);
+        let mut streamed = String::new();
+        while let Some(token) = receiver.recv().await {
+            streamed.push_str(&token);
+        }
+        let answer = Answer::from_result(&results[0]).unwrap();
+        assert_eq!(streamed, " Paris.");
+        assert_eq!(answer.text, " Paris.");
+        assert_eq!(answer.finish_reason.as_deref(), Some("eos"));
+        assert_eq!(answer.usage.unwrap().total_tokens, 9);
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({
+                "error": "invalid api key"
+            })))
+            .mount(&server)
+            .await;
+
+        let e = together(&server)
+            .search("hello", &SearchOptions::default())
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "Together API error (401): invalid api key");
+    }
+}
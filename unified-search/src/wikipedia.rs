@@ -0,0 +1,345 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::provider::{Capabilities, Error, SearchOptions, SearchProvider};
+use crate::result::UnifiedResult;
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+/// Wikimedia asks clients to identify themselves, see <https://meta.wikimedia.org/wiki/User-Agent_policy>.
+const USER_AGENT: &str = "unified-search/0.1 (command line search tool)";
+const DEFAULT_LIMIT: usize = 5;
+
+/// Accepts language edition codes such as `en`, `de` or `zh-yue`.
+pub fn parse_language(language: &str) -> Result<String, String> {
+    let valid = !language.is_empty()
+        && language
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    match valid {
+        true => Ok(language.to_string()),
+        false => Err(format!("{} is not a Wikipedia language code", language)),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SearchParams {
+    base_url: String,
+    q: String,
+    limit: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryResponse {
+    query: Query,
+}
+
+#[derive(Debug, Deserialize)]
+struct Query {
+    search: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hit {
+    title: String,
+    #[serde(default)]
+    snippet: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Summary {
+    #[serde(rename = "type")]
+    type_: String,
+    title: String,
+    description: Option<String>,
+    #[serde(default)]
+    extract: String,
+    content_urls: Option<ContentUrls>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentUrls {
+    desktop: PageUrls,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageUrls {
+    page: String,
+}
+
+pub struct Wikipedia {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl Wikipedia {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Wikipedia {
+            base_url: base_url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Searches the `language` edition, e.g. `de` for de.wikipedia.org.
+    pub fn language(language: &str) -> Self {
+        Self::new(format!("https://{}.wikipedia.org", language))
+    }
+
+    fn params(&self, query: &str, options: &SearchOptions) -> SearchParams {
+        SearchParams {
+            base_url: self.base_url.clone(),
+            q: query.to_string(),
+            limit: options.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 50),
+        }
+    }
+
+    async fn get(&self, url: reqwest::Url) -> Result<reqwest::Response, Error> {
+        let response = self
+            .client
+            .get(url)
+            .header("user-agent", USER_AGENT)
+            .send()
+            .await
+            .map_err(|e| format!("could not reach Wikipedia: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            // The action API and the REST API word their errors differently.
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| {
+                    let message = body
+                        .pointer("/error/info")
+                        .or_else(|| body.get("detail"))
+                        .or_else(|| body.get("title"))?;
+                    message.as_str().map(str::to_string)
+                })
+                .unwrap_or_else(|| body.trim().to_string());
+            return Err(format!("Wikipedia API error ({}): {}", status.as_u16(), message).into());
+        }
+        Ok(response)
+    }
+
+    /// The raw search hits of the MediaWiki action API.
+    async fn hits(&self, params: &SearchParams) -> Result<Vec<serde_json::Value>, Error> {
+        let url = reqwest::Url::parse_with_params(
+            &format!("{}/w/api.php", self.base_url),
+            &[
+                ("action", "query"),
+                ("list", "search"),
+                ("format", "json"),
+                ("formatversion", "2"),
+                ("srsearch", &params.q),
+                ("srlimit", &params.limit.to_string()),
+            ],
+        )?;
+        let response: QueryResponse = self.get(url).await?.json().await?;
+        Ok(response.query.search)
+    }
+
+    /// The lead section of the article titled `title`, from the REST API.
+    async fn summary(&self, title: &str) -> Result<serde_json::Value, Error> {
+        let mut url = reqwest::Url::parse(&self.base_url)?;
+        url.path_segments_mut()
+            .map_err(|_| "Wikipedia base URL can't have a path")?
+            .extend([
+                "api",
+                "rest_v1",
+                "page",
+                "summary",
+                &title.replace(' ', "_"),
+            ]);
+        Ok(self.get(url).await?.json().await?)
+    }
+
+    fn page_url(&self, title: &str) -> String {
+        format!("{}/wiki/{}", self.base_url, title.replace(' ', "_"))
+    }
+
+    fn to_result(
+        &self,
+        hit: serde_json::Value,
+        summary: Option<serde_json::Value>,
+        rank: usize,
+    ) -> Result<UnifiedResult, Error> {
+        let parsed: Hit = serde_json::from_value(hit.clone())?;
+        let raw = serde_json::json!({ "search": hit, "summary": summary });
+        let summary = summary.map(serde_json::from_value::<Summary>).transpose()?;
+        let Some(summary) = summary else {
+            // Without a summary the search snippet is all there is.
+            return Ok(UnifiedResult {
+                source: "wikipedia".to_string(),
+                url: Some(self.page_url(&parsed.title)),
+                title: parsed.title,
+                snippet: strip_html(&parsed.snippet),
+                author: None,
+                published_at: None,
+                updated_at: None,
+                language: None,
+                rank,
+                score: None,
+                raw,
+                answer: None,
+            });
+        };
+        let url = match summary.content_urls {
+            Some(urls) => urls.desktop.page,
+            None => self.page_url(&summary.title),
+        };
+        // Only the label says so, the article itself is titled plainly.
+        let mut title = summary.title;
+        if summary.type_ == "disambiguation" && !title.contains("disambiguation") {
+            title += " (disambiguation)";
+        }
+        let snippet = match summary.description {
+            Some(description) => format!("{}\n{}", description, summary.extract),
+            None => summary.extract,
+        };
+        Ok(UnifiedResult {
+            source: "wikipedia".to_string(),
+            url: Some(url),
+            title,
+            snippet,
+            author: None,
+            published_at: None,
+            updated_at: None,
+            language: None,
+            rank,
+            score: None,
+            raw,
+            answer: None,
+        })
+    }
+}
+
+/// Search snippets are HTML with the matches highlighted.
+fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&quot;", "\"")
+        .replace("&#039;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[async_trait]
+impl SearchProvider for Wikipedia {
+    fn name(&self) -> &'static str {
+        "wikipedia"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            links: true,
+            answer: true,
+            ..Default::default()
+        }
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(7 * 24 * 60 * 60)
+    }
+
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        serde_json::to_value(self.params(query, options)).unwrap_or_default()
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error> {
+        let hits = self.hits(&self.params(query, options)).await?;
+        let summaries = futures::future::join_all(hits.iter().map(|hit| async {
+            let title = hit.get("title")?.as_str()?;
+            // A missing summary only costs the extract, the hit is still shown.
+            self.summary(title).await.ok()
+        }))
+        .await;
+        hits.into_iter()
+            .zip(summaries)
+            .enumerate()
+            .map(|(index, (hit, summary))| self.to_result(hit, summary, index + 1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_with_summaries() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("list", "search"))
+            .and(query_param("srsearch", "Mercury"))
+            .and(query_param("srlimit", "5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "batchcomplete": true,
+                "query": {
+                    "search": [
+                        { "ns": 0, "title": "Mercury (planet)", "pageid": 19694, "snippet": "<span class=\"searchmatch\">Mercury</span> is the first planet" },
+                        { "ns": 0, "title": "Mercury", "pageid": 19919, "snippet": "<span class=\"searchmatch\">Mercury</span> may refer to" },
+                        { "ns": 0, "title": "Freddie Mercury", "pageid": 42068, "snippet": "Freddie <span class=\"searchmatch\">Mercury</span> &amp; Queen" }
+                    ]
+                }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/rest_v1/page/summary/Mercury_(planet)"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "type": "standard",
+                "title": "Mercury (planet)",
+                "description": "First planet from the Sun",
+                "extract": "Mercury is the first planet from the Sun and the smallest in the Solar System.",
+                "content_urls": { "desktop": { "page": "https://en.wikipedia.org/wiki/Mercury_(planet)" } }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/rest_v1/page/summary/Mercury"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "type": "disambiguation",
+                "title": "Mercury",
+                "extract": "Mercury may refer to:"
+            })))
+            .mount(&server)
+            .await;
+
+        let results = Wikipedia::new(server.uri())
+            .search("Mercury", &SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].title, "Mercury (planet)");
+        assert_eq!(
+            results[0].snippet,
+            "First planet from the Sun\nMercury is the first planet from the Sun and the smallest in the Solar System."
+        );
+        assert_eq!(results[1].title, "Mercury (disambiguation)");
+        assert_eq!(
+            results[1].url,
+            Some(format!("{}/wiki/Mercury", server.uri()))
+        );
+        // No summary mock, so the search snippet is used.
+        assert_eq!(results[2].snippet, "Freddie Mercury & Queen");
+        assert!(parse_language("zh-yue").is_ok());
+        assert!(parse_language("en.evil.com/").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use search_youtube::{Order, SafeSearch, YoutubeClient, YoutubeSearchRequest};
+
+use crate::provider::{Capabilities, Error, SearchOptions, SearchProvider};
+use crate::result::UnifiedResult;
+
+pub struct Youtube {
+    request: YoutubeSearchRequest,
+    client: Option<YoutubeClient>,
+}
+
+impl Youtube {
+    /// Searches with the filters of `request`, its query is replaced by the one searched for.
+    ///
+    /// The client is configured from the environment, `YOUTUBE_API_KEY`.
+    pub fn new(request: YoutubeSearchRequest) -> Self {
+        Youtube {
+            request,
+            client: YoutubeClient::from_env().ok(),
+        }
+    }
+
+    /// The same defaults as the `youtube` command line.
+    pub fn default_request() -> YoutubeSearchRequest {
+        YoutubeSearchRequest {
+            part: Some("snippet".to_string()),
+            max_results: Some(25),
+            order: Some(Order::Relevance),
+            safe_search: Some(SafeSearch::Moderate),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_client(client: YoutubeClient, request: YoutubeSearchRequest) -> Self {
+        Youtube {
+            request,
+            client: Some(client),
+        }
+    }
+
+    fn request(&self, query: &str, options: &SearchOptions) -> YoutubeSearchRequest {
+        let mut request = YoutubeSearchRequest {
+            q: Some(query.to_string()),
+            ..self.request.clone()
+        };
+        if let Some(limit) = options.limit {
+            // The total to fetch; it is requested in pages of at most 50.
+            request.max_results = Some(limit.max(1) as i64);
+        }
+        request
+    }
+}
+
+impl Default for Youtube {
+    fn default() -> Self {
+        Youtube::new(Youtube::default_request())
+    }
+}
+
+#[async_trait]
+impl SearchProvider for Youtube {
+    fn name(&self) -> &'static str {
+        "youtube"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            links: true,
+            ..Default::default()
+        }
+    }
+
+    fn is_configured(&self) -> bool {
+        self.client.is_some()
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(6 * 60 * 60)
+    }
+
+    fn request_params(&self, query: &str, options: &SearchOptions) -> serde_json::Value {
+        serde_json::to_value(self.request(query, options)).unwrap_or_default()
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnifiedResult>, Error> {
+        let request = self.request(query, options);
+        let client = self.client.as_ref().ok_or("YOUTUBE_API_KEY must be set")?;
+        let total = request.max_results.unwrap_or(25).max(1) as usize;
+        let items = client.search_all(request, total).await?;
+        Ok(items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| UnifiedResult::from_youtube_item(item, index + 1))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_from_fixture() {
+        let mut youtube = Youtube {
+            request: Youtube::default_request(),
+            client: None,
+        };
+        let options = SearchOptions {
+            limit: Some(3),
+            ..Default::default()
+        };
+        let request = youtube.request("rust programming", &options);
+        let (_server, client) = search_youtube::replay::replay_in(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../search-youtube/fixtures"),
+            "rust_programming",
+            &request,
+        )
+        .await;
+        youtube.client = Some(client);
+        let results = youtube.search("rust programming", &options).await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].url.as_deref(),
+            Some("https://www.youtube.com/watch?v=tLv5hD4SVjE")
+        );
+    }
+}